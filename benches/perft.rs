@@ -0,0 +1,36 @@
+//! times deeper perfts than `chessica perft` to catch speed regressions, run with
+//! `cargo bench`. a node count that's off fails the bench, so a faster but wrong
+//! move generation doesn't pass for an improvement
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use chessica::fen;
+use chessica::perft::check;
+
+/// the positions with their node counts at depths 4 and 5
+const BENCH: [(&str, &str, [u64; 2]); 2] = [
+    ("start", fen::STARTING_FEN, [197_281, 4_865_609]),
+    (
+        "kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        [4_085_603, 193_690_690],
+    ),
+];
+
+fn perft(c: &mut Criterion) {
+    let mut group = c.benchmark_group("perft");
+    // a kiwipete perft(5) takes seconds, the fewest samples criterion takes are plenty
+    group.sample_size(10);
+    for (name, fen, counts) in BENCH {
+        for (depth, expected) in (4..).zip(counts) {
+            group.throughput(Throughput::Elements(expected));
+            group.bench_function(format!("{name} perft({depth})"), |b| {
+                b.iter(|| check(fen, depth, expected).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, perft);
+criterion_main!(benches);
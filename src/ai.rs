@@ -0,0 +1,1715 @@
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::chess::{squares, Board, Color, Piece, PieceKind};
+use crate::r#move::Move;
+use crate::transposition::{Bound, Entry, Replacement, TranspositionTable};
+
+/// the score of giving mate right away; mates further down the line score a bit less
+pub const MATE: f32 = 10000.0;
+
+impl Board {
+    /// material balance, positive in favor of white
+    pub fn rate(&self) -> f32 {
+        self.material_balance()
+    }
+
+    /// `rate` with the piece values of `params`
+    pub fn rate_with(&self, params: &EvalParams) -> f32 {
+        let material = self.material();
+        PieceKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let difference = material.count(Color::White, kind) as f32
+                    - material.count(Color::Black, kind) as f32;
+                difference * params.piece_values[kind as usize]
+            })
+            .sum()
+    }
+
+    /// the material the side playing the capture `mv` wins on its target square, in
+    /// centipawns, if both sides keep recapturing with their least valuable piece for as
+    /// long as it pays off. sliders behind the pieces taking part join in once uncovered.
+    /// 0 for castles and moves from empty squares
+    pub fn see(&self, mv: Move) -> i32 {
+        let (from, to, victim) = match mv {
+            Move::AB { from, to } | Move::Promotion { from, to, .. } => {
+                (from, to, self[to].piece_kind())
+            }
+            Move::EnPassant { from, to, .. } => (from, to, Some(PieceKind::Pawn)),
+            Move::Castle { .. } => return 0,
+        };
+        let (Some(mut side), Some(mut on_square)) = (
+            Color::from_bits(self[from].color()),
+            self[from].piece_kind(),
+        ) else {
+            return 0;
+        };
+        let mut occupied =
+            (self.occupancy(Color::White) | self.occupancy(Color::Black)) & !(1 << from);
+        if let Move::EnPassant { capture, .. } = mv {
+            occupied &= !(1 << capture);
+        }
+        // gains[i] is what the side making the i-th capture wins if the exchange stopped there
+        let mut gains = vec![victim.map_or(0, see_value)];
+        loop {
+            side = side.opposite();
+            let attackers = self.attackers_bitboard(to, side, occupied);
+            let Some((square, kind)) = PieceKind::ALL.into_iter().find_map(|kind| {
+                let of_kind = attackers & self.bitboard(side, kind);
+                (of_kind != 0).then(|| (of_kind.trailing_zeros(), kind))
+            }) else {
+                break;
+            };
+            gains.push(see_value(on_square) - gains[gains.len() - 1]);
+            // a captured king ends the exchange
+            if on_square == PieceKind::King {
+                break;
+            }
+            on_square = kind;
+            occupied &= !(1 << square);
+        }
+        // either side may stop recapturing instead of going on at a loss
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap();
+            let before = gains.len() - 1;
+            gains[before] = -(-gains[before]).max(last);
+        }
+        gains[0]
+    }
+
+    /// `rate` plus the piece-square table bonuses, positive in favor of white
+    pub fn rate_positional(&self) -> f32 {
+        let mut bonus = 0;
+        for (i, p) in self.occupied() {
+            if p.is(Piece::WHITE) {
+                bonus += square_value(p.kind(), i);
+            } else {
+                // the tables are from white's point of view, flip the rank for black
+                bonus -= square_value(p.kind(), i ^ 56);
+            }
+        }
+        self.rate() + bonus as f32 / 100.0
+    }
+
+    /// `rate` plus piece-square bonuses blended from the middlegame and the endgame tables
+    /// by how much non-pawn material is left, plus `rate_pawns`. positive in favor of white
+    pub fn rate_tapered(&self) -> f32 {
+        self.rate_tapered_with(&EvalParams::default())
+    }
+
+    /// `rate_tapered` with the piece values and weights of `params`
+    pub fn rate_tapered_with(&self, params: &EvalParams) -> f32 {
+        let (mut middlegame, mut endgame) = (0, 0);
+        for (i, p) in self.occupied() {
+            let (square, sign) = if p.is(Piece::WHITE) {
+                (i, 1)
+            } else {
+                (i ^ 56, -1)
+            };
+            middlegame += sign * square_value(p.kind(), square);
+            endgame += sign * endgame_square_value(p.kind(), square);
+        }
+        let phase = self.phase();
+        let bonus = (middlegame * phase + endgame * (FULL_PHASE - phase)) / FULL_PHASE;
+        self.rate_with(params)
+            + params.square_table_weight * bonus as f32 / 100.0
+            + self.rate_pawns_with(params)
+    }
+
+    /// how much non-pawn material is left, from 0 with none to `FULL_PHASE` with at
+    /// least that of the starting position
+    pub fn phase(&self) -> i32 {
+        let material = self.material();
+        let phase: i32 = PieceKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let count = material.count(Color::White, kind) + material.count(Color::Black, kind);
+                count as i32 * phase_weight(kind)
+            })
+            .sum();
+        phase.min(FULL_PHASE)
+    }
+
+    /// `phase`, put into words
+    pub fn game_phase(&self) -> Phase {
+        match self.phase() {
+            phase if phase >= OPENING_PHASE => Phase::Opening,
+            phase if phase > ENDGAME_PHASE => Phase::Middlegame,
+            _ => Phase::Endgame,
+        }
+    }
+
+    /// bonuses for passed pawns and penalties for doubled and isolated ones,
+    /// positive in favor of white
+    pub fn rate_pawns(&self) -> f32 {
+        self.rate_pawns_with(&EvalParams::default())
+    }
+
+    /// `rate_pawns` with the weights of `params`
+    pub fn rate_pawns_with(&self, params: &EvalParams) -> f32 {
+        let pawns: Vec<(usize, Color)> = self
+            .occupied()
+            .filter(|(_, p)| p.is(Piece::PAWN))
+            .filter_map(|(i, p)| Some((i, Color::from_bits(p.color())?)))
+            .collect();
+        let mut per_file = [[0; Board::NUM_FILES as usize]; 2];
+        for &(i, color) in &pawns {
+            per_file[color as usize][Board::file_of(i)] += 1;
+        }
+        let mut score = 0.0;
+        for &(i, color) in &pawns {
+            let file = Board::file_of(i);
+            let sign = if color == Color::White { 1.0 } else { -1.0 };
+            let neighbours = file.saturating_sub(1)..=(file + 1).min(Board::NUM_FILES as usize - 1);
+            if neighbours
+                .clone()
+                .all(|f| f == file || per_file[color as usize][f] == 0)
+            {
+                score -= sign * params.isolated_pawn_penalty;
+            }
+            if self.is_passed_pawn(i) {
+                score += sign * params.passed_pawn_bonus;
+            }
+        }
+        for color in [Color::White, Color::Black] {
+            let sign = if color == Color::White { 1.0 } else { -1.0 };
+            let doubled: i32 = per_file[color as usize]
+                .iter()
+                .map(|&n| (n - 1).max(0))
+                .sum();
+            score -= sign * params.doubled_pawn_penalty * doubled as f32;
+        }
+        score
+    }
+
+    /// whether there is a pawn on `square` that no enemy pawn can stop by blocking or
+    /// capturing it, i.e. none stands ahead of it on its own or a neighbouring file
+    pub fn is_passed_pawn(&self, square: usize) -> bool {
+        let p = self[square];
+        let Some(color) = Color::from_bits(p.color()).filter(|_| p.is(Piece::PAWN)) else {
+            return false;
+        };
+        let (file, rank) = (Board::file_of(square), Board::rank_of(square));
+        !squares(self.bitboard(color.opposite(), PieceKind::Pawn)).any(|enemy| {
+            let ahead = match color {
+                Color::White => Board::rank_of(enemy) > rank,
+                Color::Black => Board::rank_of(enemy) < rank,
+            };
+            ahead && Board::file_of(enemy).abs_diff(file) <= 1
+        })
+    }
+
+    /// the squares of `color`'s passed pawns, see `is_passed_pawn`
+    pub fn passed_pawns(&self, color: Color) -> Vec<usize> {
+        squares(self.bitboard(color, PieceKind::Pawn))
+            .filter(|&square| self.is_passed_pawn(square))
+            .collect()
+    }
+
+    /// the best move for the side to move according to a `depth` plies deep minimax
+    /// search, `None` if there are no legal moves
+    pub fn best_move(&self, depth: u32) -> Option<Move> {
+        self.search(depth).map(|result| result.best)
+    }
+
+    /// `best_move` along with its score and the line both sides are expected to play
+    pub fn search(&self, depth: u32) -> Option<SearchResult> {
+        let mut search = Search::new(None, None);
+        let (best, score) = search.root(self, depth, None)?;
+        Some(SearchResult {
+            best,
+            score,
+            pv: search.pv.swap_remove(0),
+            depth,
+            nodes: search.nodes,
+        })
+    }
+
+    /// like `best_move` but looks up and stores positions in `table`,
+    /// which may be kept around between searches
+    pub fn best_move_with_table(&self, depth: u32, table: &TranspositionTable) -> Option<Move> {
+        Search::new(None, Some(table))
+            .root(self, depth, None)
+            .map(|(mv, _)| mv)
+    }
+
+    /// the best move after searching for `millis` milliseconds
+    pub fn best_move_timed(&self, millis: u64) -> Option<Move> {
+        let limits = SearchLimits {
+            time_ms: Some(millis),
+            ..SearchLimits::default()
+        };
+        self.search_with_limits(limits).map(|result| result.best)
+    }
+
+    /// searches 1, 2, 3, ... plies deep until one of `limits` is hit and returns the
+    /// result of the deepest search that finished. after the first depth, an unfinished
+    /// search is thrown away as soon as the time or node limit is hit
+    pub fn search_with_limits(&self, limits: SearchLimits) -> Option<SearchResult> {
+        self.search_with_info(limits, |_| {})
+    }
+
+    /// `search_with_limits`, calling `on_iteration` whenever a depth is done
+    pub fn search_with_info(
+        &self,
+        limits: SearchLimits,
+        on_iteration: impl FnMut(SearchInfo),
+    ) -> Option<SearchResult> {
+        let table = TranspositionTable::new(TABLE_SIZE, Replacement::DepthPreferred);
+        let deadline = limits
+            .time_ms
+            .map(|millis| Instant::now() + Duration::from_millis(millis));
+        let mut search = Search::new(deadline, Some(&table));
+        search.max_nodes = limits.nodes;
+        search.aspiration_window = limits.aspiration_window.map(|cp| cp as f32 / 100.0);
+        search.params = limits.eval;
+        let contempt = limits.contempt as f32 / 100.0;
+        search.draw_score = if self.turn() == Color::White {
+            -contempt
+        } else {
+            contempt
+        };
+        let max_depth = limits.depth.unwrap_or(MAX_DEPTH).min(MAX_DEPTH);
+        search.deepen(self, 1..=max_depth, on_iteration)
+    }
+
+    /// a `depth` plies deep search on `threads` threads sharing a transposition table
+    /// (lazy SMP). the result is the one of the main thread, which the helpers only
+    /// speed up by filling the table with positions it's going to need
+    pub fn search_parallel(&self, depth: u32, threads: usize) -> Option<SearchResult> {
+        let table = TranspositionTable::new(TABLE_SIZE, Replacement::DepthPreferred);
+        let stop = AtomicBool::new(false);
+        let depth = depth.min(MAX_DEPTH);
+        thread::scope(|scope| {
+            for helper in 1..threads {
+                let (table, stop) = (&table, &stop);
+                scope.spawn(move || {
+                    let mut search = Search::new(None, Some(table));
+                    search.stop = Some(stop);
+                    // every other helper starts a ply deeper so they don't all search
+                    // the same positions in the same order
+                    let first_depth = 1 + helper as u32 % 2;
+                    search.deepen(self, first_depth..=depth, |_| {});
+                });
+            }
+            let mut search = Search::new(None, Some(&table));
+            let result = search.deepen(self, 1..=depth, |_| {});
+            stop.store(true, Ordering::Relaxed);
+            result
+        })
+    }
+}
+
+/// the weights of the evaluation terms, all in pawns
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EvalParams {
+    /// indexed by `PieceKind as usize`
+    pub piece_values: [f32; 6],
+    /// scales the piece-square table bonuses, 1 to use them as they are
+    pub square_table_weight: f32,
+    /// for every pawn beyond the first on a file
+    pub doubled_pawn_penalty: f32,
+    /// for a pawn without friendly pawns on the neighbouring files
+    pub isolated_pawn_penalty: f32,
+    /// for a pawn no enemy pawn can stop by blocking or capturing
+    pub passed_pawn_bonus: f32,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self {
+            piece_values: PieceKind::ALL.map(PieceKind::value),
+            square_table_weight: 1.0,
+            doubled_pawn_penalty: 0.2,
+            isolated_pawn_penalty: 0.15,
+            passed_pawn_bonus: 0.3,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub best: Move,
+    /// the rating the search arrived at, positive in favor of white
+    pub score: f32,
+    /// the principal variation: `best` and the expected replies and moves after it.
+    /// it may end early where the transposition table cut the search short
+    pub pv: Vec<Move>,
+    /// how many plies deep the search went, not counting extensions and quiescence
+    pub depth: u32,
+    /// the positions visited on the way
+    pub nodes: u64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+/// how far `Board::search_with_info` got, reported after every depth
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: u32,
+    /// positive in favor of white
+    pub score: f32,
+    /// the positions visited so far, counting the shallower depths
+    pub nodes: u64,
+    pub pv: Vec<Move>,
+}
+
+/// when `Board::search_with_limits` stops, whichever limit comes first, and how it
+/// scores draws. `None` for no limit, though the depth never exceeds `MAX_DEPTH`
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub time_ms: Option<u64>,
+    /// how many centipawns worse than equal a draw is for the side to move at the root.
+    /// positive to play on for a win against weaker opponents, negative to settle for
+    /// draws against stronger ones
+    pub contempt: i32,
+    /// after the first depth, how many centipawns around the previous score the next
+    /// depth is searched, widening the window only where the score falls outside of it.
+    /// `None` to always search the full window
+    pub aspiration_window: Option<u32>,
+    /// the weights the positions the search stops at are rated with
+    pub eval: EvalParams,
+}
+
+/// iterative deepening gives up after this many plies even if there's time left
+const MAX_DEPTH: u32 = 64;
+
+/// the number of transposition table entries of a timed search
+const TABLE_SIZE: usize = 1 << 16;
+
+/// aspiration windows wider than this many pawns aren't worth it over the full window
+const MAX_ASPIRATION_WINDOW: f32 = 5.0;
+
+/// a line is extended by at most this many plies for checks
+const MAX_EXTENSIONS: u32 = 4;
+
+/// how `Search` orders the moves it tries, to compare the orderings against each other
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum MoveOrdering {
+    /// the moves as generated
+    #[cfg_attr(not(test), allow(dead_code))]
+    Unordered,
+    /// captures by MVV-LVA, then the quiet moves as generated
+    #[cfg_attr(not(test), allow(dead_code))]
+    Captures,
+    /// captures by MVV-LVA, then the killers and the quiet moves by history
+    Full,
+}
+
+/// how many plies shallower than the real moves the null move is searched
+const NULL_MOVE_REDUCTION: u32 = 2;
+
+/// scores this close to `MATE` are mates, which are stored relative to the position
+/// in the transposition table rather than to the root
+const MATE_THRESHOLD: f32 = MATE - 1000.0;
+
+struct Search<'a> {
+    /// searches running past this point in time return garbage and have to be ignored
+    deadline: Option<Instant>,
+    /// the same for searches visiting more positions than this
+    max_nodes: Option<u64>,
+    /// the positions visited so far, by `alphabeta` and `quiescence`
+    nodes: u64,
+    /// the score of drawn positions, 0 unless there's contempt for draws
+    draw_score: f32,
+    /// the initial half width of the window around the previous depth's score, in pawns
+    aspiration_window: Option<f32>,
+    table: Option<&'a TranspositionTable>,
+    /// set by another thread once this search isn't needed anymore
+    stop: Option<&'a AtomicBool>,
+    /// per ply, the last two quiet moves that caused a beta cutoff
+    killers: [[Option<Move>; 2]; MAX_DEPTH as usize + 1],
+    /// how much the quiet moves from one square to another caused cutoffs so far,
+    /// indexed by the from and to square
+    history: Box<[[u32; Board::NUM_SQUARES]; Board::NUM_SQUARES]>,
+    /// per ply, the best line found from the position currently searched at that ply
+    pv: Vec<Vec<Move>>,
+    /// whether the position being entered was reached by a null move, to not pass twice
+    after_null_move: bool,
+    /// the check extensions on the line currently searched
+    extensions: u32,
+    /// how many check extensions a line may have, `MAX_EXTENSIONS` unless switched off
+    max_extensions: u32,
+    /// the weights `evaluate` rates positions with
+    params: EvalParams,
+    /// how the moves of every position are ordered before trying them
+    ordering: MoveOrdering,
+}
+
+impl<'a> Search<'a> {
+    fn new(deadline: Option<Instant>, table: Option<&'a TranspositionTable>) -> Self {
+        Self {
+            deadline,
+            max_nodes: None,
+            nodes: 0,
+            draw_score: 0.0,
+            aspiration_window: None,
+            table,
+            stop: None,
+            killers: [[None; 2]; MAX_DEPTH as usize + 1],
+            history: Box::new([[0; Board::NUM_SQUARES]; Board::NUM_SQUARES]),
+            pv: Vec::new(),
+            after_null_move: false,
+            extensions: 0,
+            max_extensions: MAX_EXTENSIONS,
+            params: EvalParams::default(),
+            ordering: MoveOrdering::Full,
+        }
+    }
+
+    /// whether the deadline passed, the node budget is spent or another thread said so
+    fn should_stop(&self) -> bool {
+        self.max_nodes.is_some_and(|max| self.nodes >= max)
+            || self.deadline.is_some_and(|d| Instant::now() >= d)
+            || self.stop.is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+
+    /// searches every depth of `depths` in turn and returns the result of the deepest
+    /// one that finished. after the first depth, an unfinished search is thrown away
+    /// as soon as it's stopped
+    fn deepen(
+        &mut self,
+        board: &Board,
+        depths: RangeInclusive<u32>,
+        mut on_iteration: impl FnMut(SearchInfo),
+    ) -> Option<SearchResult> {
+        let mut result: Option<SearchResult> = None;
+        for depth in depths {
+            let best = match (&result, self.aspiration_window) {
+                (Some(previous), Some(window)) => {
+                    self.aspirate(board, depth, previous.best, previous.score, window)
+                }
+                _ => self.root(board, depth, result.as_ref().map(|r| r.best)),
+            };
+            if self.should_stop() && result.is_some() {
+                break;
+            }
+            let (best, score) = best?;
+            on_iteration(SearchInfo {
+                depth,
+                score,
+                nodes: self.nodes,
+                pv: self.pv[0].clone(),
+            });
+            result = Some(SearchResult {
+                best,
+                score,
+                pv: self.pv[0].clone(),
+                depth,
+                nodes: 0,
+            });
+            if self.should_stop() {
+                break;
+            }
+        }
+        let nodes = self.nodes;
+        result.map(|result| SearchResult { nodes, ..result })
+    }
+
+    /// `root` with a window of `window` pawns both ways around the expected `score`,
+    /// searched again with a window four times as wide while the score falls outside of
+    /// it, and with the full window once it's wider than `MAX_ASPIRATION_WINDOW`
+    fn aspirate(
+        &mut self,
+        board: &Board,
+        depth: u32,
+        mut first: Move,
+        score: f32,
+        mut window: f32,
+    ) -> Option<(Move, f32)> {
+        while window <= MAX_ASPIRATION_WINDOW {
+            let (alpha, beta) = (score - window, score + window);
+            let best = self.root_within(board, depth, Some(first), alpha, beta)?;
+            if (alpha < best.1 && best.1 < beta) || self.should_stop() {
+                return Some(best);
+            }
+            first = best.0;
+            window *= 4.0;
+        }
+        self.root(board, depth, Some(first))
+    }
+
+    /// the best move `depth` plies deep and its score, searching `first` before all
+    /// other moves. its line ends up in `pv[0]`
+    fn root(&mut self, board: &Board, depth: u32, first: Option<Move>) -> Option<(Move, f32)> {
+        self.root_within(board, depth, first, f32::NEG_INFINITY, f32::INFINITY)
+    }
+
+    /// `root`, with a score outside of (alpha, beta) only a bound of the real one
+    fn root_within(
+        &mut self,
+        board: &Board,
+        depth: u32,
+        first: Option<Move>,
+        mut alpha: f32,
+        mut beta: f32,
+    ) -> Option<(Move, f32)> {
+        self.clear_pv(0);
+        let white = board.turn() == Color::White;
+        let mut best: Option<(Move, f32)> = None;
+        let mut moves = board.gen_legal(board.turn());
+        if self.ordering != MoveOrdering::Unordered {
+            order_moves(board, &mut moves);
+        }
+        if let Some(i) = moves.iter().position(|&mv| Some(mv) == first) {
+            moves[..=i].rotate_right(1);
+        }
+        let mut board = board.clone();
+        for mv in moves {
+            let undo = board.make_move(mv);
+            let score = self.child(&mut board, depth.saturating_sub(1), 1, alpha, beta);
+            board.unmake_move(mv, undo);
+            // strictly better only, so that the first of equally good moves is picked
+            // just like an unpruned minimax would
+            if best.is_none_or(|(_, s)| if white { score > s } else { score < s }) {
+                best = Some((mv, score));
+                self.update_pv(0, mv);
+                if white {
+                    alpha = alpha.max(score);
+                } else {
+                    beta = beta.min(score);
+                }
+            }
+            if alpha >= beta || self.should_stop() {
+                break;
+            }
+        }
+        best
+    }
+
+    /// minimax with alpha-beta pruning; white maximizes, black minimizes.
+    /// a result outside of (alpha, beta) is only a bound of the real score.
+    /// `ply` is the distance to the root, used to prefer faster mates
+    fn alphabeta(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        ply: u32,
+        mut alpha: f32,
+        mut beta: f32,
+    ) -> f32 {
+        self.clear_pv(ply);
+        let after_null_move = std::mem::take(&mut self.after_null_move);
+        if self.should_stop() {
+            return 0.0;
+        }
+        self.nodes += 1;
+        if board.is_repetition() || board.is_fifty_move_draw() || board.is_insufficient_material() {
+            return self.draw_score;
+        }
+        let mut moves = board.gen_legal(board.turn());
+        let white = board.turn() == Color::White;
+        if moves.is_empty() {
+            return if !board.is_check(board.turn()) {
+                self.draw_score
+            } else if white {
+                -(MATE - ply as f32)
+            } else {
+                MATE - ply as f32
+            };
+        }
+        if depth == 0 {
+            return self.quiescence(board, alpha, beta);
+        }
+        let hash = board.zobrist_hash();
+        let entry = self.table.as_ref().and_then(|t| t.get(hash));
+        if let Some(entry) = entry.filter(|e| e.depth >= depth) {
+            let score = from_table_score(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => {}
+            }
+        }
+        // if passing still gets a cutoff from a shallower search, a real move would too.
+        // that doesn't hold in check or in pawn endings, where zugzwang is common
+        if !after_null_move
+            && depth > NULL_MOVE_REDUCTION
+            && !board.is_check(board.turn())
+            && has_pieces(board, board.turn())
+        {
+            let undo = board.make_null_move();
+            self.after_null_move = true;
+            let score =
+                self.alphabeta(board, depth - 1 - NULL_MOVE_REDUCTION, ply + 1, alpha, beta);
+            board.unmake_null_move(undo);
+            if (white && score >= beta) || (!white && score <= alpha) {
+                return score;
+            }
+        }
+        self.order_moves(board, &mut moves, ply);
+        if let Some(i) = entry.and_then(|e| moves.iter().position(|&mv| Some(mv) == e.best_move)) {
+            moves[..=i].rotate_right(1);
+        }
+        let (original_alpha, original_beta) = (alpha, beta);
+        let mut best_move = None;
+        for mv in moves {
+            let undo = board.make_move(mv);
+            let score = self.child(board, depth - 1, ply + 1, alpha, beta);
+            board.unmake_move(mv, undo);
+            if white && score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+                self.update_pv(ply, mv);
+            } else if !white && score < beta {
+                beta = score;
+                best_move = Some(mv);
+                self.update_pv(ply, mv);
+            }
+            if alpha >= beta {
+                if mv.is_quiet(board) {
+                    self.store_cutoff(mv, depth, ply);
+                }
+                break;
+            }
+        }
+        let score = if white { alpha } else { beta };
+        let stopped = self.should_stop();
+        if let Some(table) = self.table.filter(|_| !stopped) {
+            let bound = if score <= original_alpha {
+                Bound::Upper
+            } else if score >= original_beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            table.insert(Entry {
+                hash,
+                depth,
+                score: to_table_score(score, ply),
+                bound,
+                best_move,
+            });
+        }
+        score
+    }
+
+    /// `alphabeta` of the position a move just led to, a ply deeper if that move gave
+    /// check so that forcing lines aren't cut off right before they pay off
+    fn child(&mut self, board: &mut Board, depth: u32, ply: u32, alpha: f32, beta: f32) -> f32 {
+        let extension =
+            u32::from(self.extensions < self.max_extensions && board.is_check(board.turn()));
+        self.extensions += extension;
+        let score = self.alphabeta(board, depth + extension, ply, alpha, beta);
+        self.extensions -= extension;
+        score
+    }
+
+    /// empties the line of `ply`, making sure there is one for it and the ply after it
+    fn clear_pv(&mut self, ply: u32) {
+        let ply = ply as usize;
+        if self.pv.len() <= ply + 1 {
+            self.pv.resize(ply + 2, Vec::new());
+        }
+        self.pv[ply].clear();
+    }
+
+    /// makes `mv` followed by the line of the next ply the line of `ply`
+    fn update_pv(&mut self, ply: u32, mv: Move) {
+        let ply = ply as usize;
+        let (line, rest) = self.pv[ply..].split_at_mut(1);
+        line[0].clear();
+        line[0].push(mv);
+        line[0].extend_from_slice(&rest[0]);
+    }
+
+    /// `order_moves`, with the quiet moves ordered killers first and then by history
+    fn order_moves(&self, board: &Board, moves: &mut [Move], ply: u32) {
+        match self.ordering {
+            MoveOrdering::Unordered => return,
+            MoveOrdering::Captures => return order_moves(board, moves),
+            MoveOrdering::Full => {}
+        }
+        let killers = self.killers.get(ply as usize).copied().unwrap_or_default();
+        moves.sort_by_cached_key(|&mv| {
+            let killer = killers.iter().rev().position(|&k| k == Some(mv));
+            let (from, to) = move_squares(mv);
+            std::cmp::Reverse((mvv_lva(board, mv), killer, self.history[from][to]))
+        });
+    }
+
+    /// remembers the quiet `mv` for having caused a beta cutoff `depth` plies from the leaves
+    fn store_cutoff(&mut self, mv: Move, depth: u32, ply: u32) {
+        if self.ordering != MoveOrdering::Full {
+            return;
+        }
+        if let Some(killers) = self.killers.get_mut(ply as usize) {
+            if killers[0] != Some(mv) {
+                killers[1] = killers[0];
+                killers[0] = Some(mv);
+            }
+        }
+        let (from, to) = move_squares(mv);
+        self.history[from][to] += depth * depth;
+    }
+
+    /// the static rating of the positions the search stops at, positive in favor of white
+    fn evaluate(&self, board: &Board) -> f32 {
+        board.rate_tapered_with(&self.params)
+    }
+
+    /// follows up the leaves of `alphabeta` with captures only until the position is
+    /// quiet, so that it doesn't stop in the middle of an exchange. the side to move may
+    /// also decline to capture at all, taking the static rating (standing pat)
+    fn quiescence(&mut self, board: &mut Board, mut alpha: f32, mut beta: f32) -> f32 {
+        if self.should_stop() {
+            return 0.0;
+        }
+        self.nodes += 1;
+        let white = board.turn() == Color::White;
+        let stand_pat = self.evaluate(board);
+        if white {
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+        } else {
+            if stand_pat <= alpha {
+                return stand_pat;
+            }
+            beta = beta.min(stand_pat);
+        }
+        let mut captures = board.gen_captures(board.turn());
+        if self.ordering != MoveOrdering::Unordered {
+            order_moves(board, &mut captures);
+        }
+        for mv in captures {
+            let undo = board.make_move(mv);
+            let score = self.quiescence(board, alpha, beta);
+            board.unmake_move(mv, undo);
+            if white {
+                alpha = alpha.max(score);
+            } else {
+                beta = beta.min(score);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        if white {
+            alpha
+        } else {
+            beta
+        }
+    }
+}
+
+/// whether `color` has anything besides its king and pawns
+fn has_pieces(board: &Board, color: Color) -> bool {
+    board
+        .pieces_of(color)
+        .any(|(_, p)| !matches!(p.piece_kind(), Some(PieceKind::King | PieceKind::Pawn)))
+}
+
+/// mate scores count the plies from the root; the table stores them counted from
+/// the position itself so that they stay right when reached through another path
+fn to_table_score(score: f32, ply: u32) -> f32 {
+    if score > MATE_THRESHOLD {
+        score + ply as f32
+    } else if score < -MATE_THRESHOLD {
+        score - ply as f32
+    } else {
+        score
+    }
+}
+
+fn from_table_score(score: f32, ply: u32) -> f32 {
+    if score > MATE_THRESHOLD {
+        score - ply as f32
+    } else if score < -MATE_THRESHOLD {
+        score + ply as f32
+    } else {
+        score
+    }
+}
+
+/// sorts captures to the front, most valuable victim first and among those the least
+/// valuable attacker first (MVV-LVA). the order of the other moves is kept
+pub fn order_moves(board: &Board, moves: &mut [Move]) {
+    moves.sort_by_key(|&mv| std::cmp::Reverse(mvv_lva(board, mv)));
+}
+
+/// the squares `mv` moves a piece from and to, the king's for castles
+fn move_squares(mv: Move) -> (usize, usize) {
+    match mv {
+        Move::AB { from, to }
+        | Move::EnPassant { from, to, .. }
+        | Move::Promotion { from, to, .. } => (from, to),
+        Move::Castle { ty } => (ty.king_from(), ty.king_to()),
+    }
+}
+
+/// the MVV-LVA score of `mv` on `board`, 0 for quiet moves
+fn mvv_lva(board: &Board, mv: Move) -> u32 {
+    let (from, victim) = match mv {
+        Move::AB { from, to } | Move::Promotion { from, to, .. } => (from, board[to].piece_kind()),
+        Move::EnPassant { from, .. } => (from, Some(PieceKind::Pawn)),
+        Move::Castle { .. } => return 0,
+    };
+    let Some(victim) = victim else {
+        return 0;
+    };
+    8 * capture_rank(victim) - board[from].piece_kind().map_or(0, capture_rank)
+}
+
+fn capture_rank(kind: PieceKind) -> u32 {
+    match kind {
+        PieceKind::Pawn => 1,
+        PieceKind::Knight => 2,
+        PieceKind::Bishop => 3,
+        PieceKind::Rook => 4,
+        PieceKind::Queen => 5,
+        PieceKind::King => 6,
+    }
+}
+
+/// material values for `Board::see` in centipawns. taking the king outweighs everything,
+/// so capturing with the king onto a defended square never pays off
+fn see_value(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::King => MATE as i32,
+        kind => (kind.value() * 100.0) as i32,
+    }
+}
+
+/// the piece-square table bonus of a white piece of `kind` on `square`, in centipawns
+pub fn square_value(kind: u16, square: usize) -> i32 {
+    let table = match kind {
+        Piece::PAWN => &PAWN_TABLE,
+        Piece::KNIGHT => &KNIGHT_TABLE,
+        Piece::BISHOP => &BISHOP_TABLE,
+        Piece::ROOK => &ROOK_TABLE,
+        Piece::QUEEN => &QUEEN_TABLE,
+        Piece::KING => &KING_TABLE,
+        _ => return 0,
+    };
+    table[square] as i32
+}
+
+/// like `square_value`, but for positions with little material left
+pub fn endgame_square_value(kind: u16, square: usize) -> i32 {
+    match kind {
+        Piece::PAWN => PAWN_ENDGAME_TABLE[square] as i32,
+        Piece::KING => KING_ENDGAME_TABLE[square] as i32,
+        _ => square_value(kind, square),
+    }
+}
+
+/// the phase of the starting position, where only the middlegame tables count
+pub const FULL_PHASE: i32 = 24;
+/// at least this phase is an opening, with no more than a minor piece traded off
+const OPENING_PHASE: i32 = 23;
+/// at most this phase is an endgame, e.g. a rook and a minor piece each
+const ENDGAME_PHASE: i32 = 6;
+
+/// how much a piece of `kind` moves the game phase towards the middlegame
+fn phase_weight(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::Pawn | PieceKind::King => 0,
+        PieceKind::Knight | PieceKind::Bishop => 1,
+        PieceKind::Rook => 2,
+        PieceKind::Queen => 4,
+    }
+}
+
+pub fn piece_value(kind: u16) -> f32 {
+    PieceKind::from_bits(kind).map_or(0.0, PieceKind::value)
+}
+
+// piece-square tables in centipawns, laid out like the board (a8 first)
+#[rustfmt::skip]
+const PAWN_TABLE: [i16; Board::NUM_SQUARES] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i16; Board::NUM_SQUARES] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+#[rustfmt::skip]
+const BISHOP_TABLE: [i16; Board::NUM_SQUARES] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+#[rustfmt::skip]
+const ROOK_TABLE: [i16; Board::NUM_SQUARES] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+#[rustfmt::skip]
+const QUEEN_TABLE: [i16; Board::NUM_SQUARES] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+#[rustfmt::skip]
+const KING_TABLE: [i16; Board::NUM_SQUARES] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+#[rustfmt::skip]
+const PAWN_ENDGAME_TABLE: [i16; Board::NUM_SQUARES] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    80, 80, 80, 80, 80, 80, 80, 80,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    30, 30, 30, 30, 30, 30, 30, 30,
+    20, 20, 20, 20, 20, 20, 20, 20,
+    10, 10, 10, 10, 10, 10, 10, 10,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [i16; Board::NUM_SQUARES] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_board;
+    use crate::r#move::Move;
+
+    fn see(fen: &str, uci: &str) -> i32 {
+        let board = parse_board(fen).unwrap();
+        board.see(Move::from_uci(uci, &board).unwrap())
+    }
+
+    #[test]
+    fn piece_square_tables_prefer_the_center() {
+        let rim = parse_board("4k3/8/8/8/8/8/8/N3K3 w - - 0 1").unwrap();
+        let center = parse_board("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        assert!(center.rate_positional() > rim.rate_positional());
+        assert_eq!(center.rate(), rim.rate());
+        // the tables are mirrored for black: a black knight on d5 is worth what
+        // a white one on d4 is, for the other side
+        let black = parse_board("4k3/8/8/3n4/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(black.rate_positional(), -center.rate_positional());
+        assert_eq!(center.mirror().rate_positional(), -center.rate_positional());
+    }
+
+    #[test]
+    fn search_uses_the_piece_square_tables() {
+        // a knight move to the center beats one to the rim, which only the tables tell apart
+        let board = parse_board("4k3/7p/8/8/8/8/7P/1N2K3 w - - 0 1").unwrap();
+        let best = board.best_move(1).unwrap();
+        assert!(
+            ["b1c3", "b1d2"].contains(&best.to_uci().as_str()),
+            "{best:?}"
+        );
+        assert_ne!(board.search(2).unwrap().score, 0.0);
+    }
+
+    #[test]
+    fn see_of_captures() {
+        // an undefended knight is won outright
+        assert_eq!(see("4k3/8/8/3n4/8/8/8/3RK3 w - - 0 1", "d1d5"), 300);
+        // a pawn defended by a pawn costs the rook taking it
+        assert_eq!(see("4k3/8/4p3/3p4/8/8/8/3RK3 w - - 0 1", "d1d5"), -400);
+        // a defended queen is still worth taking with a pawn
+        assert_eq!(see("4k3/8/4p3/3q4/4P3/8/8/4K3 w - - 0 1", "e4d5"), 800);
+        // the king can't recapture on a defended square
+        assert_eq!(see("8/8/4k3/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), 100);
+        assert_eq!(see(crate::fen::STARTING_FEN, "e2e4"), 0);
+    }
+
+    #[test]
+    fn see_counts_x_ray_attackers() {
+        // the rook on d1 only joins once the one on d2 took
+        assert_eq!(see("3r1k2/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), 100);
+        assert_eq!(see("3r1k2/8/8/3p4/8/8/3R4/4K3 w - - 0 1", "d2d5"), -400);
+        // and so does black's queen behind the rook
+        assert_eq!(see("3q1k2/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), -400);
+    }
+
+    #[test]
+    fn search_centralizes_the_king_in_the_endgame() {
+        // the middlegame tables keep the king on the back rank, the endgame ones
+        // bring it forward, and without pieces left they're all that counts
+        let board = parse_board("4k3/p7/P7/8/8/8/1K6/8 w - - 0 1").unwrap();
+        assert_eq!(board.phase(), 0);
+        assert_eq!(board.best_move(1).unwrap().to_uci(), "b2c3");
+    }
+
+    #[test]
+    fn search_develops_from_the_starting_position() {
+        // with material alone every quiet first move rates 0 and the first one generated,
+        // a2a3, wins. the tables and pawn terms prefer the center
+        let result = Board::default().search(5).unwrap();
+        assert!(
+            ["d2d4", "e2e4", "b1c3", "g1f3"].contains(&result.best.to_uci().as_str()),
+            "{:?}",
+            result.best
+        );
+        assert!(result.score > 0.0);
+    }
+
+    #[test]
+    fn search_rates_with_the_limits_eval_params() {
+        let board = parse_board("4k3/8/8/8/8/8/P7/4K3 w - - 0 1").unwrap();
+        let score = |eval| {
+            let limits = SearchLimits {
+                depth: Some(2),
+                eval,
+                ..SearchLimits::default()
+            };
+            board.search_with_limits(limits).unwrap().score
+        };
+        let mut heavy_pawns = EvalParams::default();
+        heavy_pawns.piece_values[PieceKind::Pawn as usize] *= 2.0;
+        let (default, heavy) = (score(EvalParams::default()), score(heavy_pawns));
+        assert!((heavy - default - 1.0).abs() < 0.01, "{default} {heavy}");
+    }
+
+    #[test]
+    fn rate_counts_material() {
+        assert_eq!(Board::default().rate(), 0.0);
+        assert_eq!(
+            parse_board("4k3/8/8/8/8/8/8/3QK3 w - - 0 1")
+                .unwrap()
+                .rate(),
+            9.0
+        );
+        assert_eq!(
+            parse_board("4k3/3q4/8/8/8/8/8/4K3 w - - 0 1")
+                .unwrap()
+                .rate(),
+            -9.0
+        );
+        let minor_pieces = parse_board("4k3/3nb3/8/8/8/8/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(minor_pieces.rate(), -1.0);
+        // the kings don't count
+        assert_eq!(
+            parse_board("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap().rate(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn search_takes_a_hanging_queen() {
+        let board = parse_board("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(board.best_move(2).unwrap().to_uci(), "d1d5");
+        // black saves it instead
+        let board = parse_board("4k3/8/8/3q4/8/8/8/3RK3 b - - 0 1").unwrap();
+        let best = board.best_move(2).unwrap();
+        assert!(board.with_move(best).rate() < 0.0, "{best:?}");
+        // no moves when checkmated
+        let mated =
+            parse_board("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        assert!(mated.best_move(2).is_none());
+    }
+
+    /// the score of `board` after a search `depth` plies deep without any pruning, but
+    /// otherwise like `Search::alphabeta`: the same draws, mates, check extensions and
+    /// quiescence at the leaves. no null moves either, which at the depths tested here
+    /// `alphabeta` doesn't try anyway since only positions in check get that deep
+    fn minimax(search: &mut Search, board: &mut Board, depth: u32, ply: u32) -> f32 {
+        search.nodes += 1;
+        if board.is_repetition() || board.is_fifty_move_draw() || board.is_insufficient_material() {
+            return search.draw_score;
+        }
+        let moves = board.gen_legal(board.turn());
+        let white = board.turn() == Color::White;
+        if moves.is_empty() {
+            return if !board.is_check(board.turn()) {
+                search.draw_score
+            } else if white {
+                -(MATE - ply as f32)
+            } else {
+                MATE - ply as f32
+            };
+        }
+        if depth == 0 {
+            return search.quiescence(board, f32::NEG_INFINITY, f32::INFINITY);
+        }
+        let mut best = if white {
+            f32::NEG_INFINITY
+        } else {
+            f32::INFINITY
+        };
+        for mv in moves {
+            let undo = board.make_move(mv);
+            let score = minimax_child(search, board, depth - 1, ply + 1);
+            board.unmake_move(mv, undo);
+            best = if white {
+                best.max(score)
+            } else {
+                best.min(score)
+            };
+        }
+        best
+    }
+
+    /// `Search::child` for `minimax`
+    fn minimax_child(search: &mut Search, board: &mut Board, depth: u32, ply: u32) -> f32 {
+        let extension =
+            u32::from(search.extensions < search.max_extensions && board.is_check(board.turn()));
+        search.extensions += extension;
+        let score = minimax(search, board, depth + extension, ply);
+        search.extensions -= extension;
+        score
+    }
+
+    /// the move `minimax` rates best, the first of equally good ones in the order
+    /// `Board::search` tries them, its score and the number of positions visited
+    fn minimax_root(board: &Board, depth: u32) -> (Move, f32, u64) {
+        let mut search = Search::new(None, None);
+        let white = board.turn() == Color::White;
+        let mut moves = board.gen_legal(board.turn());
+        order_moves(board, &mut moves);
+        let mut board = board.clone();
+        let mut best: Option<(Move, f32)> = None;
+        for mv in moves {
+            let undo = board.make_move(mv);
+            let score = minimax_child(&mut search, &mut board, depth - 1, 1);
+            board.unmake_move(mv, undo);
+            if best.is_none_or(|(_, s)| if white { score > s } else { score < s }) {
+                best = Some((mv, score));
+            }
+        }
+        let (mv, score) = best.unwrap();
+        (mv, score, search.nodes)
+    }
+
+    #[test]
+    fn alpha_beta_agrees_with_minimax() {
+        for fen in [
+            crate::fen::STARTING_FEN,
+            "4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1",
+            "6k1/5ppp/8/7q/8/8/5PPP/R3R1K1 w - - 0 1",
+            "4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 1",
+        ] {
+            let board = parse_board(fen).unwrap();
+            let (best, score, minimax_nodes) = minimax_root(&board, 3);
+            let result = board.search(3).unwrap();
+            assert_eq!(board.best_move(3), Some(best), "{fen}");
+            assert_eq!((result.best, result.score), (best, score), "{fen}");
+            assert!(
+                result.nodes < minimax_nodes,
+                "{fen}: {} of {minimax_nodes} nodes",
+                result.nodes
+            );
+        }
+    }
+
+    #[test]
+    fn search_prefers_faster_mates() {
+        // Ra8# right away, though taking the queen on h5 first mates as well
+        let board = parse_board("6k1/5ppp/8/7q/8/8/5PPP/R3R1K1 w - - 0 1").unwrap();
+        let result = board.search(3).unwrap();
+        assert!(board.with_move(result.best).is_checkmate(Color::Black));
+        assert_eq!(result.score, MATE - 1.0);
+        // a mate one ply further away scores one ply less
+        let board = parse_board("7k/8/6K1/8/8/8/8/R7 b - - 0 1").unwrap();
+        let result = board.search(3).unwrap();
+        assert_eq!(result.score, MATE - 2.0);
+    }
+
+    #[test]
+    fn quiescence_sees_the_recapture() {
+        // at depth 1 the queen takes the pawn unless the search looks at exd5 too
+        let board = parse_board("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_ne!(board.best_move(1).unwrap().to_uci(), "d1d5");
+        // while an undefended pawn is still taken
+        let board = parse_board("4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(board.best_move(1).unwrap().to_uci(), "d1d5");
+    }
+
+    #[test]
+    fn order_moves_puts_the_best_captures_first() {
+        let board = parse_board("7k/8/8/3q1n2/2P1Q3/8/8/4K3 w - - 0 1").unwrap();
+        let mut moves = board.gen_legal(Color::White);
+        order_moves(&board, &mut moves);
+        let uci: Vec<String> = moves.iter().map(Move::to_uci).collect();
+        // the queen by the pawn, then by the queen, then the knight
+        assert_eq!(uci[..3], ["c4d5", "e4d5", "e4f5"]);
+        assert!(moves[3..].iter().all(|mv| !mv.is_capture(&board)));
+        // the quiet moves keep their order
+        let quiet: Vec<Move> = board
+            .gen_legal(Color::White)
+            .into_iter()
+            .filter(|mv| !mv.is_capture(&board))
+            .collect();
+        assert_eq!(moves[3..], quiet);
+    }
+
+    #[test]
+    fn timed_search_stops_in_time() {
+        let board =
+            parse_board("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let start = Instant::now();
+        let best = board.best_move_timed(50).unwrap();
+        assert!(
+            start.elapsed() < Duration::from_millis(500),
+            "{:?}",
+            start.elapsed()
+        );
+        assert!(board.gen_legal(Color::White).contains(&best));
+        // more time goes into searching deeper
+        let timed = |time_ms| {
+            let limits = SearchLimits {
+                time_ms: Some(time_ms),
+                ..SearchLimits::default()
+            };
+            board.search_with_limits(limits).unwrap().depth
+        };
+        let (short, long) = (timed(10), timed(1000));
+        assert!(short < long, "depth {short} in 10ms, {long} in 1000ms");
+        let mated =
+            parse_board("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        assert!(mated.best_move_timed(10).is_none());
+    }
+
+    #[test]
+    fn the_table_doesnt_change_the_best_move() {
+        for fen in [
+            crate::fen::STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "6k1/5ppp/8/7q/8/8/5PPP/R3R1K1 w - - 0 1",
+        ] {
+            let board = parse_board(fen).unwrap();
+            for depth in 1..=3 {
+                let table = TranspositionTable::new(1 << 12, Replacement::DepthPreferred);
+                assert_eq!(
+                    board.best_move(depth),
+                    board.best_move_with_table(depth, &table),
+                    "{fen} {depth}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn principal_variation_ends_in_mate() {
+        // the rooks mate in two on their own
+        let board = parse_board("6k1/8/8/8/8/8/R7/1R4K1 w - - 0 1").unwrap();
+        let result = board.search(4).unwrap();
+        assert_eq!(result.pv[0], result.best);
+        let mut after = board.clone();
+        for &mv in &result.pv {
+            assert!(after.is_legal(mv), "{:?}", result.pv);
+            after.make_move(mv);
+        }
+        assert!(after.is_checkmate(after.turn()), "{:?}", result.pv);
+        assert_eq!(result.score, MATE - result.pv.len() as f32);
+    }
+
+    #[test]
+    fn null_move_pruning_still_finds_mates_in_zugzwang() {
+        // the defending side has nothing but king moves, each of which makes it worse
+        for fen in [
+            "7k/8/5K2/8/8/8/8/6Q1 w - - 0 1",
+            "8/8/8/8/8/6K1/4R3/6k1 w - - 0 1",
+            "k7/8/1K6/8/8/8/8/7R w - - 0 1",
+        ] {
+            let board = parse_board(fen).unwrap();
+            let result = board.search(4).unwrap();
+            let mut after = board.clone();
+            for &mv in &result.pv {
+                after.make_move(mv);
+            }
+            assert!(result.score > MATE_THRESHOLD, "{fen} {}", result.score);
+            assert!(after.is_checkmate(after.turn()), "{fen} {:?}", result.pv);
+        }
+    }
+
+    #[test]
+    fn check_extensions_find_longer_mates() {
+        // Re8+ Rxe8 Rxe8# is three plies but found at depth 2, the checks being extended
+        let board = parse_board("r5k1/5ppp/8/8/8/8/4RPPP/4R1K1 w - - 0 1").unwrap();
+        let result = board.search(2).unwrap();
+        assert_eq!(result.best.to_uci(), "e2e8");
+        assert!(
+            result.score > MATE_THRESHOLD,
+            "{:?} {}",
+            result.pv,
+            result.score
+        );
+        // without them, the mate is beyond the horizon
+        let (_, score, _) = search_with(&board, 2, |search| search.max_extensions = 0);
+        assert!(score < MATE_THRESHOLD, "{score}");
+    }
+
+    #[test]
+    fn evaluation_is_symmetric() {
+        for fen in [
+            crate::fen::STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        ] {
+            let board = parse_board(fen).unwrap();
+            let mirrored = board.mirror();
+            assert_eq!(board.rate(), -mirrored.rate(), "{fen}");
+            assert_eq!(
+                board.rate_positional(),
+                -mirrored.rate_positional(),
+                "{fen}"
+            );
+            assert_eq!(board.rate_tapered(), -mirrored.rate_tapered(), "{fen}");
+            assert_eq!(mirrored.mirror(), board);
+            assert_eq!(
+                mirrored.count_legal_moves(mirrored.turn()),
+                board.count_legal_moves(board.turn())
+            );
+        }
+        let mirrored = parse_board("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w Kq d6 0 3")
+            .unwrap()
+            .mirror();
+        assert_eq!(
+            crate::fen::to_fen(&mirrored),
+            "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b Qk d3 0 3"
+        );
+    }
+
+    #[test]
+    fn tapered_evaluation_moves_the_king_with_the_phase() {
+        let rate = |fen| parse_board(fen).unwrap().rate_tapered();
+        // with only pawns left the central king is the better one
+        assert!(
+            rate("4k3/4p3/8/8/4K3/8/4P3/8 w - - 0 1") > rate("4k3/4p3/8/8/8/8/4P3/6K1 w - - 0 1")
+        );
+        // with all the pieces on the board the castled one is
+        assert!(
+            rate("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1RK1 w kq - 0 1")
+                > rate("rnbqkbnr/pppppppp/8/8/4K3/8/PPPPPPPP/RNBQ1R2 w kq - 0 1")
+        );
+        // the full phase rates with the middlegame tables alone
+        let board = Board::default();
+        assert_eq!(board.phase(), FULL_PHASE);
+        assert_eq!(
+            board.rate_tapered(),
+            board.rate_positional() + board.rate_pawns()
+        );
+    }
+
+    #[test]
+    fn pawn_structure_terms() {
+        let rate = |fen| parse_board(fen).unwrap().rate_pawns();
+        // doubled pawns on the c-file against the same pawns on a and c
+        assert!(
+            rate("4k3/pp6/8/8/8/P7/2P5/4K3 w - - 0 1")
+                > rate("4k3/pp6/8/8/8/2P5/2P5/4K3 w - - 0 1")
+        );
+        // a lone passed pawn gets the bonus though it's isolated
+        let params = EvalParams::default();
+        assert_eq!(
+            rate("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1"),
+            params.passed_pawn_bonus - params.isolated_pawn_penalty
+        );
+        assert_eq!(rate(crate::fen::STARTING_FEN), 0.0);
+        let doubled = parse_board("4k3/pp6/8/8/8/2P5/2P5/4K3 w - - 0 1").unwrap();
+        assert_eq!(doubled.rate_pawns(), -doubled.mirror().rate_pawns());
+    }
+
+    #[test]
+    fn incremental_material_matches_a_full_count() {
+        let params = EvalParams::default();
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+        ] {
+            let mut board = parse_board(fen).unwrap();
+            let mut played = Vec::new();
+            // captures first, to get through promotions and en passant
+            for ply in 0..40 {
+                let mut moves = board.gen_captures(board.turn());
+                if moves.is_empty() {
+                    moves = board.gen_legal(board.turn());
+                }
+                if moves.is_empty() {
+                    break;
+                }
+                let mv = moves[ply * 3 % moves.len()];
+                played.push((mv, board.make_move(mv)));
+                assert_eq!(board.material_balance(), board.rate_with(&params), "{mv}");
+            }
+            while let Some((mv, undo)) = played.pop() {
+                board.unmake_move(mv, undo);
+                assert_eq!(board.material_balance(), board.rate_with(&params), "{mv}");
+            }
+        }
+    }
+
+    #[test]
+    fn search_stops_at_the_depth_or_node_limit() {
+        let board =
+            parse_board("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let depth = board
+            .search_with_limits(SearchLimits {
+                depth: Some(3),
+                ..SearchLimits::default()
+            })
+            .unwrap();
+        assert_eq!(depth.depth, 3);
+        let nodes = board
+            .search_with_limits(SearchLimits {
+                nodes: Some(5000),
+                ..SearchLimits::default()
+            })
+            .unwrap();
+        assert!(nodes.nodes <= 5000, "{}", nodes.nodes);
+        assert!((1..10).contains(&nodes.depth), "{}", nodes.depth);
+        assert!(board.gen_legal(Color::White).contains(&nodes.best));
+    }
+
+    #[test]
+    fn search_reports_every_depth() {
+        let mut infos = Vec::new();
+        let limits = SearchLimits {
+            depth: Some(4),
+            ..SearchLimits::default()
+        };
+        let result = Board::default()
+            .search_with_info(limits, |info| infos.push(info))
+            .unwrap();
+        let depths: Vec<u32> = infos.iter().map(|info| info.depth).collect();
+        assert_eq!(depths, [1, 2, 3, 4]);
+        assert!(infos.windows(2).all(|w| w[0].nodes < w[1].nodes));
+        assert_eq!(infos[3].pv, result.pv);
+        assert_eq!(infos[3].score, result.score);
+    }
+
+    #[test]
+    fn game_phase_follows_the_material() {
+        let phase = |fen| parse_board(fen).unwrap().game_phase();
+        assert_eq!(phase(crate::fen::STARTING_FEN), Phase::Opening);
+        assert_eq!(
+            phase("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1"),
+            Phase::Middlegame
+        );
+        assert_eq!(
+            phase("4k3/pppp4/8/8/8/8/PPPP4/R3K3 w - - 0 1"),
+            Phase::Endgame
+        );
+        assert_eq!(
+            parse_board("4k3/8/8/8/8/8/8/4K3 w - - 0 1")
+                .unwrap()
+                .phase(),
+            0
+        );
+    }
+
+    #[test]
+    fn parallel_search_agrees_with_the_serial_one() {
+        for fen in [
+            "4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1",
+            "6k1/5ppp/8/7q/8/8/5PPP/R3R1K1 w - - 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        ] {
+            let board = parse_board(fen).unwrap();
+            let serial = board.search(3).unwrap();
+            for threads in [1, 4] {
+                let parallel = board.search_parallel(3, threads).unwrap();
+                assert_eq!(parallel.best, serial.best, "{fen} on {threads} threads");
+                assert_eq!(parallel.score, serial.score, "{fen} on {threads} threads");
+            }
+        }
+        let mated =
+            parse_board("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        assert!(mated.search_parallel(3, 4).is_none());
+    }
+
+    #[test]
+    fn contempt_decides_about_repetitions() {
+        let limits = |contempt| SearchLimits {
+            depth: Some(2),
+            contempt,
+            ..SearchLimits::default()
+        };
+        // Ng1 repeats the position after Nf3 Nf6 Ng1 Ng8 Nf3 Nf6 a second time
+        let mut board = Board::default();
+        board
+            .apply_uci_moves(&["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6"])
+            .unwrap();
+        let back = Move::from_uci("f3g1", &board).unwrap();
+        // a draw is as good as anything else in the equal position
+        assert_eq!(board.search_with_limits(limits(0)).unwrap().score, 0.0);
+        // settling for the repetition is the best there is with negative contempt
+        let seek = board.search_with_limits(limits(-500)).unwrap();
+        assert_eq!((seek.best, seek.score), (back, 5.0));
+        // and the worst with positive contempt
+        let avoid = board.search_with_limits(limits(500)).unwrap();
+        assert_ne!(avoid.best, back);
+        assert_eq!(avoid.score, 0.0);
+        // black's contempt is from black's point of view
+        board.make_move(back);
+        let back = Move::from_uci("f6g8", &board).unwrap();
+        let seek = board.search_with_limits(limits(-500)).unwrap();
+        assert_eq!((seek.best, seek.score), (back, -5.0));
+        assert_ne!(board.search_with_limits(limits(500)).unwrap().best, back);
+    }
+
+    #[test]
+    fn passed_pawns_of_both_sides() {
+        let board = parse_board("4k3/8/p7/8/1P4pP/6P1/8/4K3 w - - 0 1").unwrap();
+        let square = |name| Board::square_from_name(name).unwrap();
+        // a6 is ahead of b4 on a neighbouring file, nothing stops h4
+        assert!(!board.is_passed_pawn(square("b4")));
+        assert!(board.is_passed_pawn(square("h4")));
+        assert!(!board.is_passed_pawn(square("g3")));
+        // only pawns are passed
+        assert!(!board.is_passed_pawn(square("e1")));
+        assert!(!board.is_passed_pawn(square("e4")));
+        assert_eq!(board.passed_pawns(Color::White), vec![square("h4")]);
+        // ahead is down the board for black: b4 stops a6, g3 and h4 stop g4
+        assert!(board.passed_pawns(Color::Black).is_empty());
+        let board = parse_board("4k3/8/8/8/8/p7/2P5/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.passed_pawns(Color::Black), vec![square("a3")]);
+        assert_eq!(board.passed_pawns(Color::White), vec![square("c2")]);
+    }
+
+    #[test]
+    fn aspiration_windows_keep_the_result() {
+        let limits = |aspiration_window| SearchLimits {
+            depth: Some(4),
+            aspiration_window,
+            ..SearchLimits::default()
+        };
+        let (mut full_nodes, mut window_nodes) = (0, 0);
+        for fen in [
+            crate::fen::STARTING_FEN,
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+        ] {
+            let board = parse_board(fen).unwrap();
+            let full = board.search_with_limits(limits(None)).unwrap();
+            let windowed = board.search_with_limits(limits(Some(50))).unwrap();
+            assert_eq!(windowed.best, full.best, "{fen}");
+            assert_eq!(windowed.score, full.score, "{fen}");
+            full_nodes += full.nodes;
+            window_nodes += windowed.nodes;
+        }
+        assert!(window_nodes < full_nodes, "{window_nodes} >= {full_nodes}");
+    }
+
+    #[test]
+    fn killers_and_history_order_the_quiet_moves() {
+        let board = parse_board("4k3/8/8/3p4/8/8/8/R3K2R w - - 0 1").unwrap();
+        let mv = |uci| Move::from_uci(uci, &board).unwrap();
+        let mut search = Search::new(None, None);
+        search.store_cutoff(mv("h1h5"), 2, 3);
+        search.store_cutoff(mv("a1a7"), 2, 3);
+        // e1e2
+        search.history[60][52] = 100;
+        let mut moves = board.gen_legal(Color::White);
+        search.order_moves(&board, &mut moves, 3);
+        // no capture here, so the latest killer, the older one and the history move lead
+        assert_eq!(&moves[..3], [mv("a1a7"), mv("h1h5"), mv("e1e2")]);
+        // killers only count on their own ply, the history on every ply
+        search.order_moves(&board, &mut moves, 4);
+        assert_eq!(moves[0], mv("e1e2"));
+        // captures still come first
+        let board = parse_board("4k3/8/8/R2p4/8/8/8/4K2R w - - 0 1").unwrap();
+        let mut moves = board.gen_legal(Color::White);
+        search.order_moves(&board, &mut moves, 3);
+        assert_eq!(moves[0].to_uci(), "a5d5");
+        assert_eq!(moves[1].to_uci(), "h1h5");
+    }
+
+    /// the best move, its score and the nodes visited by a search of `depth`,
+    /// with the switches of `Search` set by `setup`
+    fn search_with(board: &Board, depth: u32, setup: impl FnOnce(&mut Search)) -> (Move, f32, u64) {
+        let mut search = Search::new(None, None);
+        setup(&mut search);
+        let (mv, score) = search.root(board, depth, None).unwrap();
+        (mv, score, search.nodes)
+    }
+
+    #[test]
+    fn move_ordering_shrinks_the_tree() {
+        // the queen and bishop of black both attack the knight on d4, with captures all around
+        let board =
+            parse_board("r1b1k2r/ppppnppp/2n2q2/2b5/3NP3/2P1B3/PP3PPP/RN1QKB1R w KQkq - 0 1")
+                .unwrap();
+        let (_, ordered_score, ordered) = search_with(&board, 3, |_| {});
+        let (_, unordered_score, unordered) = search_with(&board, 3, |search| {
+            search.ordering = MoveOrdering::Unordered
+        });
+        assert_eq!(ordered_score, unordered_score);
+        assert!(
+            ordered * 5 < unordered,
+            "{ordered} ordered, {unordered} unordered nodes"
+        );
+    }
+
+    #[test]
+    fn killers_and_history_shrink_quiet_trees() {
+        // positions without captures to order, where only the quiet move heuristics help
+        let (mut with, mut without) = (0, 0);
+        for fen in [
+            crate::fen::STARTING_FEN,
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 4 4",
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 1",
+            "4rrk1/pp3ppp/2p5/8/8/2P5/PP3PPP/4RRK1 w - - 0 1",
+            "8/5pk1/6p1/8/8/6P1/5PK1/8 w - - 0 1",
+        ] {
+            let board = parse_board(fen).unwrap();
+            with += search_with(&board, 4, |_| {}).2;
+            without += search_with(&board, 4, |search| search.ordering = MoveOrdering::Captures).2;
+        }
+        assert!(
+            with < without,
+            "{with} nodes with killers and history, {without} without"
+        );
+    }
+}
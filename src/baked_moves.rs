@@ -0,0 +1,141 @@
+use crate::chess::Board;
+use crate::move_gen::{offset, KING_OFFSETS, KNIGHT_OFFSETS, QUEEN_DIRECTIONS};
+
+/// the squares a knight on each square jumps to, bit n standing for index n.
+/// computed at compile time, so jumps off the edge of the board never show up
+pub const KNIGHT_MOVES: [u64; Board::NUM_SQUARES] = step_masks(&KNIGHT_OFFSETS);
+/// the squares a king on each square steps to, castling aside
+pub const KING_MOVES: [u64; Board::NUM_SQUARES] = step_masks(&KING_OFFSETS);
+/// the squares a pawn on each square attacks, indexed by `Color as usize` first.
+/// pawns on their last rank attack nothing
+pub const PAWN_ATTACKS: [[u64; Board::NUM_SQUARES]; 2] = [
+    step_masks(&[(-1, 1), (1, 1)]),
+    step_masks(&[(-1, -1), (1, -1)]),
+];
+/// the squares from each square to the edge of the board, indexed by the square and then
+/// the direction's position in `move_gen::QUEEN_DIRECTIONS`, the square itself excluded
+pub const RAYS: [[u64; 8]; Board::NUM_SQUARES] = rays();
+/// the squares strictly between two squares on a shared rank, file or diagonal,
+/// empty for squares that don't share one
+pub static BETWEEN: [[u64; Board::NUM_SQUARES]; Board::NUM_SQUARES] = between();
+
+/// the squares one of `offsets` away from each square
+const fn step_masks(offsets: &[(i32, i32)]) -> [u64; Board::NUM_SQUARES] {
+    let mut masks = [0; Board::NUM_SQUARES];
+    let mut square = 0;
+    while square < Board::NUM_SQUARES {
+        let mut i = 0;
+        while i < offsets.len() {
+            if let Some(target) = offset(square, offsets[i]) {
+                masks[square] |= 1 << target;
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    masks
+}
+
+const fn rays() -> [[u64; 8]; Board::NUM_SQUARES] {
+    let mut rays = [[0; 8]; Board::NUM_SQUARES];
+    let mut square = 0;
+    while square < Board::NUM_SQUARES {
+        let mut direction = 0;
+        while direction < QUEEN_DIRECTIONS.len() {
+            let mut target = offset(square, QUEEN_DIRECTIONS[direction]);
+            while let Some(t) = target {
+                rays[square][direction] |= 1 << t;
+                target = offset(t, QUEEN_DIRECTIONS[direction]);
+            }
+            direction += 1;
+        }
+        square += 1;
+    }
+    rays
+}
+
+// walks every ray, everything passed on the way to a square lies between the two
+const fn between() -> [[u64; Board::NUM_SQUARES]; Board::NUM_SQUARES] {
+    let mut between = [[0; Board::NUM_SQUARES]; Board::NUM_SQUARES];
+    let mut square = 0;
+    while square < Board::NUM_SQUARES {
+        let mut direction = 0;
+        while direction < QUEEN_DIRECTIONS.len() {
+            let mut passed = 0;
+            let mut target = offset(square, QUEEN_DIRECTIONS[direction]);
+            while let Some(t) = target {
+                between[square][t] = passed;
+                passed |= 1 << t;
+                target = offset(t, QUEEN_DIRECTIONS[direction]);
+            }
+            direction += 1;
+        }
+        square += 1;
+    }
+    between
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::{Board, Color};
+
+    fn square(name: &str) -> usize {
+        Board::square_from_name(name).unwrap()
+    }
+
+    fn mask(names: &[&str]) -> u64 {
+        names.iter().fold(0, |bits, name| bits | 1 << square(name))
+    }
+
+    #[test]
+    fn knight_moves_per_square() {
+        assert_eq!(KNIGHT_MOVES[square("a8")].count_ones(), 2);
+        assert_eq!(KNIGHT_MOVES[square("h1")].count_ones(), 2);
+        assert_eq!(KNIGHT_MOVES[square("b1")].count_ones(), 3);
+        assert_eq!(KNIGHT_MOVES[square("d4")].count_ones(), 8);
+        assert_eq!(KNIGHT_MOVES[square("g1")], mask(&["e2", "f3", "h3"]));
+        // 336 is the number of knight moves on an empty board
+        let total: u32 = KNIGHT_MOVES.iter().map(|m| m.count_ones()).sum();
+        assert_eq!(total, 336);
+    }
+
+    #[test]
+    fn king_moves_and_pawn_attacks() {
+        assert_eq!(KING_MOVES[square("a1")], mask(&["a2", "b1", "b2"]));
+        assert_eq!(KING_MOVES[square("h8")].count_ones(), 3);
+        assert_eq!(KING_MOVES[square("e4")].count_ones(), 8);
+        let e4 = square("e4");
+        assert_eq!(PAWN_ATTACKS[Color::White as usize][e4], mask(&["d5", "f5"]));
+        assert_eq!(PAWN_ATTACKS[Color::Black as usize][e4], mask(&["d3", "f3"]));
+        assert_eq!(
+            PAWN_ATTACKS[Color::White as usize][square("a2")],
+            mask(&["b3"])
+        );
+        assert_eq!(PAWN_ATTACKS[Color::White as usize][square("e8")], 0);
+    }
+
+    #[test]
+    fn between_and_rays() {
+        let a_file = mask(&["a2", "a3", "a4", "a5", "a6", "a7"]);
+        assert_eq!(BETWEEN[square("a1")][square("a8")], a_file);
+        assert_eq!(BETWEEN[square("a8")][square("a1")], a_file);
+        assert_eq!(
+            BETWEEN[square("a1")][square("h8")],
+            mask(&["b2", "c3", "d4", "e5", "f6", "g7"])
+        );
+        // neighbours and squares on no shared line have nothing between them
+        assert_eq!(BETWEEN[square("a1")][square("b2")], 0);
+        assert_eq!(BETWEEN[square("a1")][square("b3")], 0);
+        assert_eq!(BETWEEN[square("e4")][square("e4")], 0);
+        let all_rays = RAYS[square("d4")].iter().fold(0, |bits, ray| bits | ray);
+        assert_eq!(all_rays.count_ones(), 27);
+        assert_eq!(
+            RAYS[square("a1")]
+                .iter()
+                .map(|ray| ray.count_ones())
+                .sum::<u32>(),
+            21
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use crate::chess::{Board, Color, Piece, PieceKind};
+use crate::fen;
+use crate::r#move::CastleType;
+
+/// the length of `Board::to_bytes`: two squares per byte, then the turn and castle
+/// rights, the en passant target, both clocks and the castle rook and king files
+pub const ENCODED_LEN: usize = KING_FILES + 1;
+
+/// where the fields after the squares start in `Board::to_bytes`
+const TURN_AND_RIGHTS: usize = Board::NUM_SQUARES / 2;
+const EN_PASSANT: usize = TURN_AND_RIGHTS + 1;
+const HALFMOVE_CLOCK: usize = EN_PASSANT + 1;
+const FULLMOVE_NUMBER: usize = HALFMOVE_CLOCK + 2;
+const ROOK_FILES: usize = FULLMOVE_NUMBER + 2;
+const KING_FILES: usize = ROOK_FILES + 2;
+
+/// what `Board::to_bytes` stores for no en passant target
+const NO_EN_PASSANT: u8 = 0xff;
+
+/// black pieces have this bit set in their nibble, on top of `PieceKind as u8 + 1`
+const BLACK_NIBBLE: u8 = 0b1000;
+
+impl Board {
+    /// the position in `ENCODED_LEN` bytes, much shorter and quicker to read back than a
+    /// fen. the history of earlier positions isn't stored
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; TURN_AND_RIGHTS];
+        for (i, p) in self.occupied() {
+            bytes[i / 2] |= nibble(p) << (i % 2 * 4);
+        }
+        bytes.push(self.turn() as u8 | self.castle_rights() << 1);
+        bytes.push(
+            self.en_passant_target()
+                .map_or(NO_EN_PASSANT, |sq| sq as u8),
+        );
+        bytes.extend(self.halfmove_clock().to_le_bytes());
+        bytes.extend(self.fullmove_number().to_le_bytes());
+        let files = CastleType::all().map(|ty| self.castle_rook_file(ty) as u8);
+        bytes.push(files[0] | files[1] << 4);
+        bytes.push(files[2] | files[3] << 4);
+        let king_files =
+            [Color::White, Color::Black].map(|color| self.castle_king_file(color) as u8);
+        bytes.push(king_files[0] | king_files[1] << 4);
+        bytes
+    }
+
+    /// the board `to_bytes` encoded in `bytes`. fails for the positions
+    /// `fen::validate_position` rejects, such as ones without a king
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != ENCODED_LEN {
+            return Err(format!(
+                "encoded board has {} bytes instead of {ENCODED_LEN}",
+                bytes.len()
+            ));
+        }
+        let mut pieces = [Piece::NO_PIECE; Board::NUM_SQUARES];
+        for (i, piece) in pieces.iter_mut().enumerate() {
+            *piece = from_nibble(bytes[i / 2] >> (i % 2 * 4) & 0xf)?;
+        }
+        fen::validate_position(&pieces)?;
+        let turn = if bytes[TURN_AND_RIGHTS] & 1 == 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let castle_rights = bytes[TURN_AND_RIGHTS] >> 1;
+        if castle_rights > CastleType::fold_bits(CastleType::all()) {
+            return Err(format!("bad castle rights {castle_rights:#b}"));
+        }
+        let en_passant_target = match bytes[EN_PASSANT] {
+            NO_EN_PASSANT => None,
+            sq if usize::from(sq) < Board::NUM_SQUARES => Some(sq as usize),
+            sq => return Err(format!("bad en passant square {sq}")),
+        };
+        let halfmove_clock = u16::from_le_bytes([bytes[HALFMOVE_CLOCK], bytes[HALFMOVE_CLOCK + 1]]);
+        let fullmove_number =
+            u16::from_le_bytes([bytes[FULLMOVE_NUMBER], bytes[FULLMOVE_NUMBER + 1]]);
+        let files = [
+            bytes[ROOK_FILES] & 0xf,
+            bytes[ROOK_FILES] >> 4,
+            bytes[ROOK_FILES + 1] & 0xf,
+            bytes[ROOK_FILES + 1] >> 4,
+        ];
+        if let Some(file) = files.iter().find(|&&f| f >= Board::NUM_FILES as u8) {
+            return Err(format!("bad castle rook file {file}"));
+        }
+        let king_files = [bytes[KING_FILES] & 0xf, bytes[KING_FILES] >> 4];
+        if let Some(file) = king_files.iter().find(|&&f| f >= Board::NUM_FILES as u8) {
+            return Err(format!("bad castle king file {file}"));
+        }
+        Ok(Board::new(
+            pieces,
+            turn,
+            castle_rights,
+            en_passant_target,
+            halfmove_clock,
+            fullmove_number,
+        )
+        .with_castle_rook_files(files.map(usize::from))
+        .with_castle_king_files(king_files.map(usize::from)))
+    }
+}
+
+fn nibble(p: Piece) -> u8 {
+    let kind = p.piece_kind().map_or(0, |kind| kind as u8 + 1);
+    if p.is(Piece::BLACK) {
+        kind | BLACK_NIBBLE
+    } else {
+        kind
+    }
+}
+
+fn from_nibble(nibble: u8) -> Result<Piece, String> {
+    if nibble == 0 {
+        return Ok(Piece::NO_PIECE);
+    }
+    let color = if nibble & BLACK_NIBBLE == 0 {
+        Color::White
+    } else {
+        Color::Black
+    };
+    match PieceKind::ALL.get(((nibble & !BLACK_NIBBLE) as usize).wrapping_sub(1)) {
+        Some(kind) => Ok(Piece::new_unchecked(color.bits(), kind.to_bits())),
+        None => Err(format!("bad piece nibble {nibble:#x}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_round_trip() {
+        for fen in [
+            fen::STARTING_FEN,
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 b GBgb - 7 12",
+            "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w GEge - 0 1",
+        ] {
+            let board = fen::parse_board(fen).unwrap();
+            let bytes = board.to_bytes();
+            assert_eq!(bytes.len(), ENCODED_LEN);
+            assert_eq!(Board::from_bytes(&bytes).unwrap(), board);
+            assert_eq!(fen::to_fen(&Board::from_bytes(&bytes).unwrap()), fen);
+        }
+    }
+
+    #[test]
+    fn from_bytes_rejects_boards_without_a_king() {
+        let mut bytes = Board::default().to_bytes();
+        // e8, the black king, is the low nibble of the third byte
+        bytes[2] &= 0xf0;
+        assert!(Board::from_bytes(&bytes).is_err());
+        assert!(Board::from_bytes(&bytes[1..]).is_err());
+    }
+}
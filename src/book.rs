@@ -0,0 +1,94 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::OnceLock;
+
+use crate::chess::Board;
+use crate::r#move::Move;
+
+/// the book as lines of uci moves from the starting position. every position on a line
+/// is in the book, with the move following it on the line as one of its book moves
+const LINES: [&str; 12] = [
+    "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6",
+    "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 c2c3 g8f6",
+    "e2e4 e7e5 g1f3 b8c6 d2d4 e5d4 f3d4",
+    "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3",
+    "e2e4 c7c5 g1f3 b8c6 d2d4 c5d4 f3d4",
+    "e2e4 e7e6 d2d4 d7d5 b1c3 g8f6",
+    "e2e4 c7c6 d2d4 d7d5 b1c3 d5e4 c3e4",
+    "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6 c1g5",
+    "d2d4 d7d5 c2c4 c7c6 g1f3 g8f6",
+    "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4",
+    "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6",
+    "c2c4 e7e5 b1c3 g8f6 g1f3 b8c6",
+];
+
+impl Board {
+    /// one of the book moves of this position picked at random, `None` out of book
+    pub fn book_move(&self) -> Option<Move> {
+        let moves = book().get(&self.zobrist_hash())?;
+        let pick = RandomState::new().hash_one(self.zobrist_hash()) as usize % moves.len();
+        Some(moves[pick])
+    }
+}
+
+/// the book moves of every position of `LINES` by zobrist hash, built on first use
+fn book() -> &'static HashMap<u64, Vec<Move>> {
+    static BOOK: OnceLock<HashMap<u64, Vec<Move>>> = OnceLock::new();
+    BOOK.get_or_init(|| {
+        let mut book: HashMap<u64, Vec<Move>> = HashMap::new();
+        for line in LINES {
+            let mut board = Board::default();
+            for uci in line.split_whitespace() {
+                let mv = Move::from_uci(uci, &board).expect("book moves are valid uci");
+                debug_assert!(board.is_legal(mv), "illegal book move {uci} in '{line}'");
+                let moves = book.entry(board.zobrist_hash()).or_default();
+                if !moves.contains(&mv) {
+                    moves.push(mv);
+                }
+                board.make_move(mv);
+            }
+        }
+        book
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_board;
+
+    #[test]
+    fn book_moves_of_known_positions() {
+        let board = Board::default();
+        let first = board.book_move().unwrap().to_uci();
+        assert!(
+            ["e2e4", "d2d4", "c2c4"].contains(&first.as_str()),
+            "{first}"
+        );
+        let mut board = Board::default();
+        board.apply_uci_moves(&["e2e4"]).unwrap();
+        let reply = board.book_move().unwrap().to_uci();
+        assert!(
+            ["e7e5", "c7c5", "e7e6", "c7c6"].contains(&reply.as_str()),
+            "{reply}"
+        );
+        assert!(parse_board("4k3/8/8/8/8/8/8/4K3 w - - 0 1")
+            .unwrap()
+            .book_move()
+            .is_none());
+    }
+
+    #[test]
+    fn every_line_is_in_the_book() {
+        for line in LINES {
+            let mut board = Board::default();
+            for uci in line.split_whitespace() {
+                let mv = Move::from_uci(uci, &board).unwrap();
+                assert!(board.is_legal(mv), "{line}: {uci}");
+                assert!(book()[&board.zobrist_hash()].contains(&mv), "{line}: {uci}");
+                board.make_move(mv);
+            }
+        }
+    }
+}
@@ -1,46 +1,964 @@
 use std::fmt::{Display, Formatter};
+use std::ops::Index;
+
+use crate::r#move::{CastleType, Move};
+use crate::{fen, zobrist};
 
 #[derive(Clone, Debug)]
 pub struct Board {
     /// the pieces on the board, starting at rank 8 file a, going to rank 8 file h
     /// and ending eventually at rank 1 file h
-    pieces: [Piece; 64],
+    pieces: [Piece; Board::NUM_SQUARES],
+    /// the same pieces as one bit per square (bit 0 = a8, bit 63 = h1) for every kind,
+    /// indexed by `Color as usize` and `PieceKind as usize`
+    bitboards: [[u64; 6]; 2],
+    /// all squares taken by each color, indexed by `Color as usize`
+    occupancy: [u64; 2],
+    /// white's material minus black's in pawns, kept up to date along with the bitboards
+    material: f32,
     /// who's turn is it?
-    /// Piece::BLACK or Piece::WHITE
-    turn: u16,
+    turn: Color,
     castle_rights: u8,
+    /// the file of the rook each castle type castles with, indexed by `CastleType`.
+    /// always a and h in classic chess, but anywhere in Chess960
+    castle_rook_files: [usize; 4],
+    /// the file each color's king castles from, indexed by `Color as usize`. the e-file in
+    /// classic chess, wherever the king starts in Chess960
+    castle_king_files: [usize; 2],
+    /// the square a pawn skipped over with a double advance in the last move
+    en_passant_target: Option<usize>,
+    /// half moves since the last capture or pawn advance
+    halfmove_clock: u16,
+    /// starts at 1 and is incremented after every black move
+    fullmove_number: u16,
+    /// zobrist hash of the position, kept up to date by make_move and unmake_move
+    hash: u64,
+    /// the hashes of all positions before the current one, oldest first
+    history: Vec<u64>,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Piece(u16);
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    White,
+    Black,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+/// how many pieces of each kind both sides have, see `Board::material`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MaterialCount {
+    counts: [[u8; 6]; 2],
+}
+
+impl MaterialCount {
+    /// the number of `color`'s pieces of `kind`
+    pub fn count(&self, color: Color, kind: PieceKind) -> u8 {
+        self.counts[color as usize][kind as usize]
+    }
+
+    /// the number of `color`'s pieces, the king included
+    pub fn total(&self, color: Color) -> u8 {
+        self.counts[color as usize].iter().sum()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RenderStyle {
+    /// fen characters
+    Ascii,
+    /// chess glyphs
+    Unicode,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate { winner: Color },
+    Stalemate,
+}
+
+/// how a game ended. resignations count as wins for the other side
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw(DrawReason),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DrawReason {
+    Stalemate,
+    FiftyMoves,
+    Threefold,
+    InsufficientMaterial,
+    /// agreed on by the players, never detected on the board
+    Agreement,
+}
+
+/// sets up a `Board` piece by piece, see `Board::builder`
+#[derive(Clone, Debug)]
+pub struct BoardBuilder {
+    pieces: [Piece; Board::NUM_SQUARES],
+    turn: Color,
+    castle_rights: u8,
+    en_passant_target: Option<usize>,
+}
+
+/// what `Board::make_move` can't reconstruct from the move itself
+#[derive(Copy, Clone, Debug)]
+pub struct Undo {
+    /// the piece the move took, `Piece::NO_PIECE` if none
+    pub captured: Piece,
+    /// the castle rights, en passant target, clock and hash before the move
+    pub prev_castle: u8,
+    pub prev_ep: Option<usize>,
+    pub prev_halfmove: u16,
+    pub prev_hash: u64,
+}
+
+// the move generation relies on a8 being index 0 and h1 index 63, so a coordinate helper
+// disagreeing with that layout fails the build instead of a perft
+const _: () = {
+    let mut index = 0;
+    let (files, ranks) = (Board::NUM_FILES as usize, Board::NUM_RANKS as usize);
+    while index < Board::NUM_SQUARES {
+        let (file, rank) = (Board::file_of(index), Board::rank_of(index));
+        assert!(Board::square_index(file, rank) == index);
+        assert!(file == index % files && rank == ranks - 1 - index / files);
+        index += 1;
+    }
+};
+
+/// the squares of `rank`, from the a-file to the h-file
+const fn rank_squares(rank: usize) -> [usize; Board::NUM_FILES as usize] {
+    let mut squares = [0; Board::NUM_FILES as usize];
+    let mut file = 0;
+    while file < squares.len() {
+        squares[file] = Board::square_index(file, rank);
+        file += 1;
+    }
+    squares
+}
+
 impl Board {
     pub const NUM_FILES: u32 = 8; // = "width"
     pub const NUM_RANKS: u32 = 8; // = "height"
+    pub const NUM_SQUARES: usize = (Self::NUM_FILES * Self::NUM_RANKS) as usize;
 
-    pub fn new(pieces: [Piece; 64], turn: u16, castle_rights: u8) -> Self {
-        Self {
-            pieces,
+    pub const WHITE_BACK_RANK: [usize; Self::NUM_FILES as usize] = rank_squares(0);
+    pub const BLACK_BACK_RANK: [usize; Self::NUM_FILES as usize] =
+        rank_squares(Self::NUM_RANKS as usize - 1);
+    pub const WHITE_PAWN_RANK: [usize; Self::NUM_FILES as usize] = rank_squares(1);
+    pub const BLACK_PAWN_RANK: [usize; Self::NUM_FILES as usize] =
+        rank_squares(Self::NUM_RANKS as usize - 2);
+
+    /// `castle_rook_files` of classic chess
+    pub const CLASSIC_CASTLE_ROOK_FILES: [usize; 4] = [0, 7, 0, 7];
+    /// `castle_king_files` of classic chess
+    pub const CLASSIC_CASTLE_KING_FILES: [usize; 2] = [4, 4];
+
+    pub fn new(
+        pieces: [Piece; Board::NUM_SQUARES],
+        turn: Color,
+        castle_rights: u8,
+        en_passant_target: Option<usize>,
+        halfmove_clock: u16,
+        fullmove_number: u16,
+    ) -> Self {
+        let mut board = Self {
+            pieces: [Piece::NO_PIECE; Self::NUM_SQUARES],
+            bitboards: [[0; 6]; 2],
+            occupancy: [0; 2],
+            material: 0.0,
             turn,
             castle_rights,
+            castle_rook_files: Self::CLASSIC_CASTLE_ROOK_FILES,
+            castle_king_files: Self::CLASSIC_CASTLE_KING_FILES,
+            en_passant_target,
+            halfmove_clock,
+            fullmove_number,
+            hash: 0,
+            history: Vec::new(),
+        };
+        for (index, piece) in pieces.into_iter().enumerate() {
+            board.put(index, piece);
         }
+        board.hash = zobrist::hash(&board);
+        board
     }
-}
 
-impl Display for Board {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
-        for (i, p) in self.pieces.iter().enumerate() {
-            if i % 8 == 0 {
-                writeln!(f)?;
+    /// an empty board to put pieces on, white to move, no castle rights and no en passant
+    /// target until the builder says otherwise
+    pub fn builder() -> BoardBuilder {
+        BoardBuilder {
+            pieces: [Piece::NO_PIECE; Self::NUM_SQUARES],
+            turn: Color::White,
+            castle_rights: 0,
+            en_passant_target: None,
+        }
+    }
+
+    /// a board without any pieces, white to move and no castle rights,
+    /// to be filled with `set_piece`
+    pub fn empty() -> Self {
+        Self::new(
+            [Piece::NO_PIECE; Self::NUM_SQUARES],
+            Color::White,
+            0,
+            None,
+            0,
+            1,
+        )
+    }
+
+    /// a board with `squares` as its pieces, indexed like `squares()`, and `turn` to move.
+    /// there are no castle rights or en passant target. fails where
+    /// `fen::validate_position` does, e.g. unless both sides have exactly one king
+    pub fn from_squares(squares: [Piece; Self::NUM_SQUARES], turn: Color) -> Result<Self, String> {
+        fen::validate_position(&squares)?;
+        Ok(Self::new(squares, turn, 0, None, 0, 1))
+    }
+
+    /// the piece on every square, from a8 to h1
+    pub fn squares(&self) -> &[Piece; Self::NUM_SQUARES] {
+        &self.pieces
+    }
+
+    /// the rank of a square index, rank 0 being the first rank (a1 - h1) and
+    /// rank 7 the eighth rank (a8 - h8, which sits at the start of the pieces array)
+    pub const fn rank_of(index: usize) -> usize {
+        Self::NUM_RANKS as usize - 1 - index / Self::NUM_FILES as usize
+    }
+
+    /// the file of a square index, file 0 being the a-file
+    pub const fn file_of(index: usize) -> usize {
+        index % Self::NUM_FILES as usize
+    }
+
+    /// the square index of the given file and rank, inverse of `file_of` and `rank_of`
+    pub const fn square_index(file: usize, rank: usize) -> usize {
+        (Self::NUM_RANKS as usize - 1 - rank) * Self::NUM_FILES as usize + file
+    }
+
+    /// the name of a square index, e.g. "a8" for 0 and "h1" for 63
+    pub fn square_name(index: usize) -> String {
+        format!(
+            "{}{}",
+            (b'a' + Self::file_of(index) as u8) as char,
+            Self::rank_of(index) + 1
+        )
+    }
+
+    /// the square index of a name like "e4", inverse of `square_name`
+    pub fn square_from_name(name: &str) -> Result<usize, String> {
+        let mut chars = name.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(file @ 'a'..='h'), Some(rank @ '1'..='8'), None) => Ok(Self::square_index(
+                file as usize - 'a' as usize,
+                rank as usize - '1' as usize,
+            )),
+            _ => Err(format!("illegal square name '{name}'; a1 to h8 expected")),
+        }
+    }
+
+    /// the piece on `index`, `None` for empty squares and indices off the board
+    pub fn piece_at(&self, index: usize) -> Option<Piece> {
+        self.pieces
+            .get(index)
+            .copied()
+            .filter(|&p| p != Piece::NO_PIECE)
+    }
+
+    /// the (index, piece) pairs of all occupied squares, from a8 to h1
+    pub fn occupied(&self) -> impl Iterator<Item = (usize, Piece)> + '_ {
+        squares(self.occupancy[0] | self.occupancy[1]).map(|i| (i, self.pieces[i]))
+    }
+
+    /// the (index, piece) pairs of all pieces of `color`
+    pub fn pieces_of(&self, color: Color) -> impl Iterator<Item = (usize, Piece)> + '_ {
+        squares(self.occupancy[color as usize]).map(|i| (i, self.pieces[i]))
+    }
+
+    /// the squares of all pieces of `color` and `kind`, bit n standing for index n
+    pub fn bitboard(&self, color: Color, kind: PieceKind) -> u64 {
+        self.bitboards[color as usize][kind as usize]
+    }
+
+    /// the squares of all pieces of `color`, bit n standing for index n
+    pub fn occupancy(&self, color: Color) -> u64 {
+        self.occupancy[color as usize]
+    }
+
+    /// the material balance in pawns, positive in favor of white. kept up to date as
+    /// pieces move, so reading it doesn't walk the board
+    pub fn material_balance(&self) -> f32 {
+        self.material
+    }
+
+    /// the number of pieces of each kind on either side, counted in one pass over the
+    /// occupied squares
+    pub fn material(&self) -> MaterialCount {
+        let mut counts = [[0; 6]; 2];
+        for (_, p) in self.occupied() {
+            if let Some((color, kind)) = bitboard_slot(p) {
+                counts[color][kind] += 1;
+            }
+        }
+        MaterialCount { counts }
+    }
+
+    /// where `color`'s king stands, `None` if it has none (or the first of several)
+    pub fn king_square(&self, color: Color) -> Option<usize> {
+        squares(self.bitboard(color, PieceKind::King)).next()
+    }
+
+    pub fn turn(&self) -> Color {
+        self.turn
+    }
+
+    pub fn castle_rights(&self) -> u8 {
+        self.castle_rights
+    }
+
+    /// the board with the castling rooks on other files than a and h, for Chess960
+    pub fn with_castle_rook_files(mut self, castle_rook_files: [usize; 4]) -> Self {
+        self.castle_rook_files = castle_rook_files;
+        self
+    }
+
+    /// the board with the kings castling from other files than e, for Chess960
+    pub fn with_castle_king_files(mut self, castle_king_files: [usize; 2]) -> Self {
+        self.castle_king_files = castle_king_files;
+        self
+    }
+
+    /// the file of the rook that castles with `ty`
+    pub fn castle_rook_file(&self, ty: CastleType) -> usize {
+        self.castle_rook_files[ty as usize]
+    }
+
+    /// the square of the rook that castles with `ty`, on the back rank of its color
+    pub fn castle_rook_square(&self, ty: CastleType) -> usize {
+        Self::square_index(self.castle_rook_file(ty), Self::rank_of(ty.king_to()))
+    }
+
+    /// the file `color`'s king castles from
+    pub fn castle_king_file(&self, color: Color) -> usize {
+        self.castle_king_files[color as usize]
+    }
+
+    /// the square the king castling with `ty` starts from, on the back rank of its color
+    pub fn castle_king_square(&self, ty: CastleType) -> usize {
+        Self::square_index(
+            self.castle_king_file(ty.color()),
+            Self::rank_of(ty.king_to()),
+        )
+    }
+
+    pub fn en_passant_target(&self) -> Option<usize> {
+        self.en_passant_target
+    }
+
+    pub fn halfmove_clock(&self) -> u16 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
+
+    /// the board with rank numbers on the left and file letters at the bottom.
+    /// `flipped` shows the board from black's side, with h1 in the top left
+    pub fn render(&self, style: RenderStyle, flipped: bool) -> String {
+        self.render_marked(style, flipped, 0)
+    }
+
+    /// the `Display` layout with the squares `last_move` left and went to in brackets,
+    /// the king's for castles
+    pub fn pretty_print(&self, last_move: Option<Move>) -> String {
+        let marked = match last_move {
+            Some(
+                Move::AB { from, to }
+                | Move::EnPassant { from, to, .. }
+                | Move::Promotion { from, to, .. },
+            ) => 1 << from | 1 << to,
+            Some(Move::Castle { ty }) => 1 << self.castle_king_square(ty) | 1 << ty.king_to(),
+            None => 0,
+        };
+        self.render_marked(RenderStyle::Ascii, false, marked)
+    }
+
+    /// `render` with the squares on the `marked` bitboard in brackets
+    fn render_marked(&self, style: RenderStyle, flipped: bool, marked: u64) -> String {
+        let ranks: Vec<usize> = if flipped {
+            (0..Self::NUM_RANKS as usize).collect()
+        } else {
+            (0..Self::NUM_RANKS as usize).rev().collect()
+        };
+        let files: Vec<usize> = if flipped {
+            (0..Self::NUM_FILES as usize).rev().collect()
+        } else {
+            (0..Self::NUM_FILES as usize).collect()
+        };
+        let mut out = String::new();
+        for &rank in &ranks {
+            out.push_str(&format!("{} ", rank + 1));
+            for &file in &files {
+                let square = Self::square_index(file, rank);
+                let p = self[square];
+                let symbol = if p == Piece::NO_PIECE {
+                    String::from(".")
+                } else if style == RenderStyle::Unicode {
+                    p.to_string()
+                } else {
+                    crate::fen::fen_char(&p).to_string()
+                };
+                if marked & 1 << square != 0 {
+                    out.push_str(&format!("[{symbol}]"));
+                } else {
+                    out.push_str(&format!(" {symbol} "));
+                }
+            }
+            out.push('\n');
+        }
+        out.push_str("  ");
+        for &file in &files {
+            out.push_str(&format!(" {} ", (b'a' + file as u8) as char));
+        }
+        out.push('\n');
+        out
+    }
+
+    /// plays the given move for the side to move, the move isn't checked for legality.
+    /// the returned info can be passed to `unmake_move` to take the move back
+    pub fn make_move(&mut self, mv: Move) -> Undo {
+        let undo = Undo {
+            captured: match mv {
+                Move::AB { to, .. } | Move::Promotion { to, .. } => self.pieces[to],
+                Move::EnPassant { capture, .. } => self.pieces[capture],
+                Move::Castle { .. } => Piece::NO_PIECE,
+            },
+            prev_castle: self.castle_rights,
+            prev_ep: self.en_passant_target,
+            prev_halfmove: self.halfmove_clock,
+            prev_hash: self.hash,
+        };
+        self.history.push(self.hash);
+        self.hash ^= zobrist::castle_key(self.castle_rights)
+            ^ zobrist::en_passant_key(self)
+            ^ zobrist::turn_key(self.turn);
+        let mut en_passant_target = None;
+        let mut resets_clock = false;
+        match mv {
+            Move::AB { from, to } => {
+                let piece = self.pieces[from];
+                if piece.kind() == Piece::PAWN {
+                    resets_clock = true;
+                    if from.abs_diff(to) == 2 * Self::NUM_FILES as usize {
+                        en_passant_target = Some((from + to) / 2);
+                    }
+                }
+                resets_clock |= self.pieces[to] != Piece::NO_PIECE;
+                self.set_piece(to, piece);
+                self.clear_square(from);
+                if piece.kind() == Piece::KING {
+                    self.castle_rights &= !castle_bits(self.turn);
+                }
+                self.castle_rights &=
+                    !(self.lost_castle_rights(from) | self.lost_castle_rights(to));
             }
-            if p.is(Piece::NONE) {
-                write!(f, " . ")?;
+            Move::EnPassant { from, to, capture } => {
+                resets_clock = true;
+                self.set_piece(to, self.pieces[from]);
+                self.clear_square(from);
+                self.clear_square(capture);
+            }
+            Move::Castle { ty } => {
+                let (king_from, king_to) = (self.castle_king_square(ty), ty.king_to());
+                let (rook_from, rook_to) = (self.castle_rook_square(ty), ty.rook_to());
+                // with a Chess960 rook the king may land on its square or the rook stay
+                // where it is, so both leave before either arrives
+                let (king, rook) = (self.pieces[king_from], self.pieces[rook_from]);
+                self.clear_square(king_from);
+                self.clear_square(rook_from);
+                self.set_piece(king_to, king);
+                self.set_piece(rook_to, rook);
+                self.castle_rights &= !castle_bits(self.turn);
+            }
+            Move::Promotion { from, to, piece } => {
+                resets_clock = true;
+                self.set_piece(to, self.pieces[from].with_kind(piece));
+                self.clear_square(from);
+                self.castle_rights &= !self.lost_castle_rights(to);
+            }
+        }
+        self.en_passant_target = en_passant_target;
+        if resets_clock {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.turn == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = self.turn.opposite();
+        self.hash ^= zobrist::castle_key(self.castle_rights)
+            ^ zobrist::en_passant_key(self)
+            ^ zobrist::turn_key(self.turn);
+        undo
+    }
+
+    /// takes back `mv`, which has to be the last move made on this board
+    pub fn unmake_move(&mut self, mv: Move, undo: Undo) {
+        self.turn = self.turn.opposite();
+        if self.turn == Color::Black {
+            self.fullmove_number -= 1;
+        }
+        match mv {
+            Move::AB { from, to } => {
+                self.put(from, self.pieces[to]);
+                self.put(to, undo.captured);
+            }
+            Move::EnPassant { from, to, capture } => {
+                self.put(from, self.pieces[to]);
+                self.put(to, Piece::NO_PIECE);
+                self.put(capture, undo.captured);
+            }
+            Move::Castle { ty } => {
+                let (king_from, king_to) = (self.castle_king_square(ty), ty.king_to());
+                let (rook_from, rook_to) = (self.castle_rook_square(ty), ty.rook_to());
+                let (king, rook) = (self.pieces[king_to], self.pieces[rook_to]);
+                self.put(king_to, Piece::NO_PIECE);
+                self.put(rook_to, Piece::NO_PIECE);
+                self.put(king_from, king);
+                self.put(rook_from, rook);
+            }
+            Move::Promotion { from, to, .. } => {
+                self.put(from, self.pieces[to].with_kind(Piece::PAWN));
+                self.put(to, undo.captured);
+            }
+        }
+        self.castle_rights = undo.prev_castle;
+        self.en_passant_target = undo.prev_ep;
+        self.halfmove_clock = undo.prev_halfmove;
+        self.hash = undo.prev_hash;
+        self.history.pop();
+    }
+
+    /// hands the turn to the other side without moving anything, which the search
+    /// uses for null move pruning. the returned info can be passed to `unmake_null_move`.
+    /// the side to move mustn't be in check, or the other side could take its king next;
+    /// this isn't checked here since `is_check` is too slow for every search node
+    pub fn make_null_move(&mut self) -> Undo {
+        let undo = Undo {
+            captured: Piece::NO_PIECE,
+            prev_castle: self.castle_rights,
+            prev_ep: self.en_passant_target,
+            prev_halfmove: self.halfmove_clock,
+            prev_hash: self.hash,
+        };
+        self.history.push(self.hash);
+        self.hash ^= zobrist::en_passant_key(self) ^ zobrist::turn_key(self.turn);
+        self.en_passant_target = None;
+        self.halfmove_clock += 1;
+        if self.turn == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = self.turn.opposite();
+        self.hash ^= zobrist::turn_key(self.turn);
+        undo
+    }
+
+    /// takes back the null move `undo` was returned for
+    pub fn unmake_null_move(&mut self, undo: Undo) {
+        self.turn = self.turn.opposite();
+        if self.turn == Color::Black {
+            self.fullmove_number -= 1;
+        }
+        self.en_passant_target = undo.prev_ep;
+        self.halfmove_clock = undo.prev_halfmove;
+        self.hash = undo.prev_hash;
+        self.history.pop();
+    }
+
+    /// a copy of the board with `mv` played, this one stays as it is
+    pub fn with_move(&self, mv: Move) -> Self {
+        let mut board = self.clone();
+        board.make_move(mv);
+        board
+    }
+
+    /// the board flipped upside down with the colors swapped, i.e. the same position
+    /// seen from the other side. the history of earlier positions is not kept
+    pub fn mirror(&self) -> Self {
+        let mut pieces = [Piece::NO_PIECE; Self::NUM_SQUARES];
+        for (index, piece) in self.occupied() {
+            // only pieces of either color are on the occupancy bitboards
+            let color = if piece.is(Piece::WHITE) {
+                Piece::BLACK
             } else {
-                write!(f, " {} ", crate::fen::fen_char(p))?;
+                Piece::WHITE
+            };
+            pieces[flip_rank(index)] = piece.with_color(color);
+        }
+        let castle_rights = CastleType::fold_bits(
+            CastleType::all()
+                .into_iter()
+                .filter(|ty| self.castle_rights & ty.mirrored().get_bit() != 0),
+        );
+        let castle_rook_files = CastleType::all().map(|ty| self.castle_rook_file(ty.mirrored()));
+        let castle_king_files =
+            [Color::White, Color::Black].map(|color| self.castle_king_file(color.opposite()));
+        Self::new(
+            pieces,
+            self.turn.opposite(),
+            castle_rights,
+            self.en_passant_target.map(flip_rank),
+            self.halfmove_clock,
+            self.fullmove_number,
+        )
+        .with_castle_rook_files(castle_rook_files)
+        .with_castle_king_files(castle_king_files)
+    }
+
+    /// plays the uci `moves` one after the other, each checked for legality in the position
+    /// the ones before it lead to. the moves before a failing one stay played
+    pub fn apply_uci_moves(&mut self, moves: &[&str]) -> Result<(), String> {
+        for uci in moves {
+            let mv = Move::from_uci(uci, self)?;
+            if !self.is_legal(mv) {
+                return Err(format!("illegal move '{uci}'"));
             }
+            self.make_move(mv);
         }
         Ok(())
     }
+
+    pub fn zobrist_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// what makes positions the same for the repetition rules: the pieces, the turn, the
+    /// castle rights and the en passant file if a pawn can actually take en passant.
+    /// the clocks don't count, which is why this is just the zobrist hash
+    pub fn repetition_key(&self) -> u64 {
+        self.hash
+    }
+
+    /// whether the current position occurred at least twice before with the same
+    /// side to move, castle rights and en passant possibilities.
+    /// only positions since the last pawn move or capture can repeat
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetitions() >= 2
+    }
+
+    /// whether the current position occurred before at all, which is enough for a search
+    /// to call it a draw: whatever led back to it can be repeated
+    pub fn is_repetition(&self) -> bool {
+        self.repetitions() >= 1
+    }
+
+    /// how often the current position occurred before.
+    /// only positions since the last pawn move or capture can repeat
+    fn repetitions(&self) -> usize {
+        self.history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock as usize)
+            .filter(|&&hash| hash == self.repetition_key())
+            .count()
+    }
+
+    /// whether 50 full moves passed without a pawn move or capture
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.halfmove_clock >= 100
+    }
+
+    /// whether neither side can possibly checkmate: K vs K, K vs K+N, K vs K+B
+    /// and K+B vs K+B with both bishops on squares of the same color
+    pub fn is_insufficient_material(&self) -> bool {
+        let material = self.material();
+        let count = |kind| {
+            material.count(Color::White, kind) as usize
+                + material.count(Color::Black, kind) as usize
+        };
+        if count(PieceKind::Pawn) + count(PieceKind::Rook) + count(PieceKind::Queen) > 0 {
+            return false;
+        }
+        match (count(PieceKind::Knight), count(PieceKind::Bishop)) {
+            (0, 0) | (1, 0) | (0, 1) => true,
+            (0, 2) => {
+                let bishop = |color| self.bitboard(color, PieceKind::Bishop);
+                let (white, black) = (bishop(Color::White), bishop(Color::Black));
+                let square_color = |s: usize| (Self::file_of(s) + Self::rank_of(s)) % 2;
+                white != 0
+                    && black != 0
+                    && square_color(white.trailing_zeros() as usize)
+                        == square_color(black.trailing_zeros() as usize)
+            }
+            _ => false,
+        }
+    }
+
+    /// the castle rights that are gone once a piece moves from or to `square`
+    /// because it's the starting square of a castling rook
+    fn lost_castle_rights(&self, square: usize) -> u8 {
+        CastleType::fold_bits(CastleType::all().into_iter().filter(|&ty| {
+            let rank = if ty.color() == Color::White {
+                0
+            } else {
+                Self::NUM_RANKS as usize - 1
+            };
+            square == Self::square_index(self.castle_rook_file(ty), rank)
+        }))
+    }
+
+    /// puts `piece` on `index`, replacing whatever stood there, and keeps the hash up to date.
+    /// panics if `index` is off the board
+    pub fn set_piece(&mut self, index: usize, piece: Piece) {
+        assert!(
+            index < Self::NUM_SQUARES,
+            "square index {index} out of bounds"
+        );
+        self.hash ^=
+            zobrist::piece_key(self.pieces[index], index) ^ zobrist::piece_key(piece, index);
+        self.put(index, piece);
+    }
+
+    /// removes the piece on `index`, if any. panics if `index` is off the board
+    pub fn clear_square(&mut self, index: usize) {
+        self.set_piece(index, Piece::NO_PIECE);
+    }
+
+    /// writes the square, its bitboards and the material balance, but not the hash
+    fn put(&mut self, index: usize, piece: Piece) {
+        self.material += material_value(piece) - material_value(self.pieces[index]);
+        let bit = 1 << index;
+        if let Some((color, kind)) = bitboard_slot(self.pieces[index]) {
+            self.bitboards[color][kind] &= !bit;
+            self.occupancy[color] &= !bit;
+        }
+        if let Some((color, kind)) = bitboard_slot(piece) {
+            self.bitboards[color][kind] |= bit;
+            self.occupancy[color] |= bit;
+        }
+        self.pieces[index] = piece;
+    }
+}
+
+/// the square on the same file, as far from the other side as `index` is from its own
+fn flip_rank(index: usize) -> usize {
+    Board::square_index(
+        Board::file_of(index),
+        Board::NUM_RANKS as usize - 1 - Board::rank_of(index),
+    )
+}
+
+/// the (color, kind) indices of `piece` into `Board::bitboards`, `None` for no piece
+fn bitboard_slot(piece: Piece) -> Option<(usize, usize)> {
+    let color = Color::from_bits(piece.color())?;
+    let kind = piece.piece_kind()?;
+    Some((color as usize, kind as usize))
+}
+
+/// the value of `piece` counted for white, negative for black pieces
+fn material_value(piece: Piece) -> f32 {
+    let value = piece.piece_kind().map_or(0.0, PieceKind::value);
+    if piece.is(Piece::BLACK) {
+        -value
+    } else {
+        value
+    }
+}
+
+/// the indices of the set bits of `bitboard`, lowest first
+pub fn squares(mut bitboard: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (bitboard != 0).then(|| {
+            let square = bitboard.trailing_zeros() as usize;
+            bitboard &= bitboard - 1;
+            square
+        })
+    })
+}
+
+/// both castle rights of `color`
+fn castle_bits(color: Color) -> u8 {
+    CastleType::fold_bits(
+        CastleType::all()
+            .into_iter()
+            .filter(|ty| ty.color() == color),
+    )
+}
+
+impl BoardBuilder {
+    pub fn piece(mut self, square: usize, piece: Piece) -> Self {
+        self.pieces[square] = piece;
+        self
+    }
+
+    pub fn turn(mut self, turn: Color) -> Self {
+        self.turn = turn;
+        self
+    }
+
+    /// adds the right to castle `ty`
+    pub fn castle(mut self, ty: CastleType) -> Self {
+        self.castle_rights |= ty.get_bit();
+        self
+    }
+
+    pub fn en_passant(mut self, square: usize) -> Self {
+        self.en_passant_target = Some(square);
+        self
+    }
+
+    /// the board as set up, with the clocks of a new game. panics where `try_build` fails,
+    /// which is fine for setting up positions in tests
+    pub fn build(self) -> Board {
+        self.try_build()
+            .unwrap_or_else(|e| panic!("can't build the board: {e}"))
+    }
+
+    /// `build`, but failing where `fen::validate_position` does; the castle rights and
+    /// the en passant target aren't checked against the pieces
+    pub fn try_build(self) -> Result<Board, String> {
+        fen::validate_position(&self.pieces)?;
+        Ok(Board::new(
+            self.pieces,
+            self.turn,
+            self.castle_rights,
+            self.en_passant_target,
+            0,
+            1,
+        ))
+    }
+}
+
+/// exact equality, so the move clocks have to match too; the position history doesn't.
+/// repetitions are positions that are equal apart from the clocks
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.pieces == other.pieces
+            && self.turn == other.turn
+            && self.castle_rights == other.castle_rights
+            && self.castle_rook_files == other.castle_rook_files
+            && self.castle_king_files == other.castle_king_files
+            && self.en_passant_target == other.en_passant_target
+            && self.halfmove_clock == other.halfmove_clock
+            && self.fullmove_number == other.fullmove_number
+    }
+}
+
+impl Eq for Board {}
+
+impl Index<usize> for Board {
+    type Output = Piece;
+
+    fn index(&self, index: usize) -> &Piece {
+        debug_assert!(
+            index < Self::NUM_SQUARES,
+            "square index {index} out of bounds"
+        );
+        &self.pieces[index]
+    }
+}
+
+/// the starting position
+impl Default for Board {
+    fn default() -> Self {
+        fen::parse_board(fen::STARTING_FEN).expect("the starting fen is valid")
+    }
+}
+
+/// `render` with fen characters from white's side, every rank ending in a newline
+impl Display for Board {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.render(RenderStyle::Ascii, false))
+    }
+}
+
+impl Color {
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::White => Self::Black,
+            Self::Black => Self::White,
+        }
+    }
+
+    /// Piece::WHITE or Piece::BLACK
+    pub fn bits(self) -> u16 {
+        match self {
+            Self::White => Piece::WHITE,
+            Self::Black => Piece::BLACK,
+        }
+    }
+
+    /// the color of the Piece::WHITE or Piece::BLACK bit, `None` for anything else
+    pub fn from_bits(bits: u16) -> Option<Self> {
+        match bits {
+            Piece::WHITE => Some(Self::White),
+            Piece::BLACK => Some(Self::Black),
+            _ => None,
+        }
+    }
+}
+
+impl PieceKind {
+    pub const ALL: [PieceKind; 6] = [
+        Self::Pawn,
+        Self::Knight,
+        Self::Bishop,
+        Self::Rook,
+        Self::Queen,
+        Self::King,
+    ];
+
+    /// the material value in pawns, 0 for the king which can't be traded
+    pub fn value(self) -> f32 {
+        match self {
+            Self::Pawn => 1.0,
+            Self::Knight | Self::Bishop => 3.0,
+            Self::Rook => 5.0,
+            Self::Queen => 9.0,
+            Self::King => 0.0,
+        }
+    }
+
+    /// Piece::PAWN, ..., Piece::KING
+    pub fn to_bits(self) -> u16 {
+        match self {
+            Self::Pawn => Piece::PAWN,
+            Self::Knight => Piece::KNIGHT,
+            Self::Bishop => Piece::BISHOP,
+            Self::Rook => Piece::ROOK,
+            Self::Queen => Piece::QUEEN,
+            Self::King => Piece::KING,
+        }
+    }
+
+    /// the kind of a single kind bit, `None` for anything else
+    pub fn from_bits(bits: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.to_bits() == bits)
+    }
+}
+
+impl From<Color> for u16 {
+    fn from(color: Color) -> Self {
+        color.bits()
+    }
 }
 
 impl Piece {
@@ -54,6 +972,8 @@ impl Piece {
     pub const QUEEN: u16 = 1 << 7;
     pub const KING: u16 = 1 << 8;
 
+    pub const NO_PIECE: Piece = Piece(Piece::NONE);
+
     pub fn new_unchecked(color: u16, kind: u16) -> Self {
         Self(color | kind)
     }
@@ -62,11 +982,80 @@ impl Piece {
         self.0 & flag != 0
     }
 
+    /// alias for `is`; there's no semantic difference between the two,
+    /// both check whether any bit of `flag` is set
+    pub fn has(&self, flag: u16) -> bool {
+        self.is(flag)
+    }
+
+    /// Piece::WHITE, Piece::BLACK or 0 for an empty square
+    pub fn color(&self) -> u16 {
+        self.0 & (Self::WHITE | Self::BLACK)
+    }
+
+    /// the kind bit (Piece::PAWN, ..., Piece::KING) or 0 for an empty square
+    pub fn kind(&self) -> u16 {
+        self.0 & !(Self::WHITE | Self::BLACK | Self::NONE)
+    }
+
+    /// the kind as an enum, `None` for an empty square
+    pub fn piece_kind(&self) -> Option<PieceKind> {
+        PieceKind::from_bits(self.kind())
+    }
+
+    /// the same kind of piece in `color` (Piece::WHITE or Piece::BLACK), replacing the
+    /// old color. empty squares stay empty
+    pub fn with_color(self, color: u16) -> Self {
+        debug_assert!(
+            color == Self::WHITE || color == Self::BLACK,
+            "{color:#b} is not a single color"
+        );
+        if self.kind() == 0 {
+            return self;
+        }
+        Self(color & (Self::WHITE | Self::BLACK) | self.kind())
+    }
+
+    /// a piece of `kind` (Piece::PAWN, ..., Piece::KING) in the same color, replacing
+    /// the old kind. empty squares stay empty
+    pub fn with_kind(self, kind: u16) -> Self {
+        debug_assert!(
+            PieceKind::from_bits(kind).is_some(),
+            "{kind:#b} is not a single piece kind"
+        );
+        if self.kind() == 0 {
+            return self;
+        }
+        Self(self.color() | kind & !(Self::WHITE | Self::BLACK | Self::NONE))
+    }
+
     pub fn inner(&self) -> u16 {
         self.0
     }
 }
 
+impl Display for Piece {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let white = self.is(Self::WHITE);
+        let glyph = match self.kind() {
+            Self::PAWN if white => '♙',
+            Self::KNIGHT if white => '♘',
+            Self::BISHOP if white => '♗',
+            Self::ROOK if white => '♖',
+            Self::QUEEN if white => '♕',
+            Self::KING if white => '♔',
+            Self::PAWN => '♟',
+            Self::KNIGHT => '♞',
+            Self::BISHOP => '♝',
+            Self::ROOK => '♜',
+            Self::QUEEN => '♛',
+            Self::KING => '♚',
+            _ => ' ',
+        };
+        write!(f, "{glyph}")
+    }
+}
+
 impl From<u16> for Piece {
     fn from(n: u16) -> Self {
         Piece(n)
@@ -78,3 +1067,762 @@ impl From<Piece> for u16 {
         p.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::to_fen;
+
+    fn uci(board: &Board, mv: &str) -> Move {
+        Move::from_uci(mv, board).unwrap()
+    }
+
+    #[test]
+    fn make_move_plays_e4() {
+        let mut board = Board::default();
+        board.make_move(uci(&board, "e2e4"));
+        assert_eq!(
+            to_fen(&board),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        board.make_move(uci(&board, "g8f6"));
+        assert_eq!(
+            to_fen(&board),
+            "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2"
+        );
+    }
+
+    #[test]
+    fn unmake_move_restores_the_board() {
+        // a fixed xorshift sequence picks the moves, so failures can be replayed
+        let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for fen in [
+            crate::fen::STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1",
+        ] {
+            let mut board = crate::fen::parse_board(fen).unwrap();
+            for _ in 0..200 {
+                let moves = board.gen_legal(board.turn());
+                if moves.is_empty() {
+                    break;
+                }
+                for &mv in &moves {
+                    let before = (to_fen(&board), board.zobrist_hash(), board.clone());
+                    let undo = board.make_move(mv);
+                    board.unmake_move(mv, undo);
+                    assert_eq!(to_fen(&board), before.0, "{mv:?}");
+                    assert_eq!(board.zobrist_hash(), before.1, "{mv:?}");
+                    assert_eq!(board, before.2, "{mv:?}");
+                    // PartialEq leaves out what's derived from the pieces, compare that too
+                    for color in [Color::White, Color::Black] {
+                        assert_eq!(board.occupancy(color), before.2.occupancy(color));
+                        for kind in PieceKind::ALL {
+                            assert_eq!(board.bitboard(color, kind), before.2.bitboard(color, kind));
+                        }
+                    }
+                    assert_eq!(board.material_balance(), before.2.material_balance());
+                }
+                board.make_move(moves[next() as usize % moves.len()]);
+            }
+        }
+    }
+
+    #[test]
+    fn knight_shuffle_is_a_threefold_repetition() {
+        let mut board = Board::default();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        board.apply_uci_moves(&shuffle).unwrap();
+        assert!(board.is_repetition());
+        assert!(!board.is_threefold_repetition());
+        board.apply_uci_moves(&shuffle).unwrap();
+        assert!(board.is_threefold_repetition());
+        // a pawn move makes the earlier positions unreachable
+        board.apply_uci_moves(&["e2e4"]).unwrap();
+        assert!(!board.is_repetition());
+    }
+
+    #[test]
+    fn lost_castle_rights_break_the_repetition() {
+        let mut board = crate::fen::parse_board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        // the rooks come back, but the castle rights don't
+        let shuffle = ["a1b1", "a8b8", "b1a1", "b8a8"];
+        board.apply_uci_moves(&shuffle).unwrap();
+        board.apply_uci_moves(&shuffle).unwrap();
+        assert!(!board.is_threefold_repetition());
+        board.apply_uci_moves(&shuffle).unwrap();
+        assert!(board.is_threefold_repetition());
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let dead = |fen| {
+            crate::fen::parse_board(fen)
+                .unwrap()
+                .is_insufficient_material()
+        };
+        assert!(dead("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(dead("4k3/8/8/8/8/8/8/4KN2 w - - 0 1"));
+        assert!(dead("4kb2/8/8/8/8/8/8/4K3 w - - 0 1"));
+        // both bishops on light squares
+        assert!(dead("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(!dead("4k1b1/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(!dead("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1"));
+        assert!(!dead("4kn2/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(!dead("4k3/8/8/8/8/8/P7/4K3 w - - 0 1"));
+        assert!(!dead(crate::fen::STARTING_FEN));
+    }
+
+    #[test]
+    fn constructors_reject_boards_without_a_king() {
+        let king = |color| Piece::new_unchecked(color, Piece::KING);
+        let one_king = Board::builder().piece(60, king(Piece::WHITE));
+        assert!(one_king.clone().try_build().is_err());
+        assert!(one_king.piece(4, king(Piece::BLACK)).try_build().is_ok());
+        let mut squares = [Piece::NO_PIECE; Board::NUM_SQUARES];
+        squares[60] = king(Piece::WHITE);
+        assert!(Board::from_squares(squares, Color::White).is_err());
+        // a board still being set up is never in check
+        let mut board = Board::empty();
+        board.set_piece(60, king(Piece::WHITE));
+        assert!(!board.is_check(Color::Black));
+        assert!(!board.is_check(Color::White));
+    }
+
+    #[test]
+    fn square_names_round_trip() {
+        for index in 0..Board::NUM_SQUARES {
+            let name = Board::square_name(index);
+            assert_eq!(Board::square_from_name(&name), Ok(index), "{name}");
+            let (file, rank) = (Board::file_of(index), Board::rank_of(index));
+            assert_eq!(Board::square_index(file, rank), index);
+            assert_eq!(name.as_bytes(), [b'a' + file as u8, b'1' + rank as u8]);
+        }
+        // a8 comes first and h1 last
+        assert_eq!(Board::square_name(0), "a8");
+        assert_eq!(Board::square_name(Board::NUM_SQUARES - 1), "h1");
+        assert_eq!(Board::square_from_name("e1"), Ok(60));
+        for bad in ["", "e", "i1", "e9", "e0", "e10", "E1"] {
+            assert!(Board::square_from_name(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn piece_color_and_kind() {
+        let knight = Piece::new_unchecked(Piece::BLACK, Piece::KNIGHT);
+        assert_eq!(knight.color(), Piece::BLACK);
+        assert_eq!(knight.kind(), Piece::KNIGHT);
+        let king = Piece::new_unchecked(Piece::WHITE, Piece::KING);
+        assert_eq!(king.color(), Piece::WHITE);
+        assert_eq!(king.kind(), Piece::KING);
+        assert_eq!(Piece::NO_PIECE.color(), 0);
+        assert_eq!(Piece::NO_PIECE.kind(), 0);
+    }
+
+    #[test]
+    fn has_agrees_with_is() {
+        let rook = Piece::new_unchecked(Piece::WHITE, Piece::ROOK);
+        for flag in [
+            Piece::NONE,
+            Piece::WHITE,
+            Piece::BLACK,
+            Piece::ROOK,
+            Piece::QUEEN,
+        ] {
+            assert_eq!(rook.has(flag), rook.is(flag));
+        }
+        assert!(rook.has(Piece::ROOK | Piece::QUEEN));
+    }
+
+    #[test]
+    fn index_reads_the_square() {
+        let board = Board::default();
+        assert_eq!(board[0], Piece::new_unchecked(Piece::BLACK, Piece::ROOK));
+        assert_eq!(board[60], Piece::new_unchecked(Piece::WHITE, Piece::KING));
+        for index in 0..Board::NUM_SQUARES {
+            assert_eq!(board[index], board.squares()[index]);
+        }
+    }
+
+    #[test]
+    fn empty_squares_hold_no_piece() {
+        let board = Board::default();
+        assert_eq!(
+            board[Board::square_from_name("e4").unwrap()],
+            Piece::NO_PIECE
+        );
+        assert!(Piece::NO_PIECE.is(Piece::NONE));
+        assert!(!Piece::NO_PIECE.is(Piece::WHITE | Piece::BLACK));
+        assert_eq!(Piece::NO_PIECE.piece_kind(), None);
+    }
+
+    #[test]
+    fn geometry_helpers() {
+        // a8, h8, a1 and h1
+        assert_eq!((Board::file_of(0), Board::rank_of(0)), (0, 7));
+        assert_eq!((Board::file_of(7), Board::rank_of(7)), (7, 7));
+        assert_eq!((Board::file_of(56), Board::rank_of(56)), (0, 0));
+        assert_eq!((Board::file_of(63), Board::rank_of(63)), (7, 0));
+        // e4
+        assert_eq!(Board::square_index(4, 3), 36);
+        assert_eq!((Board::file_of(36), Board::rank_of(36)), (4, 3));
+    }
+
+    #[test]
+    fn rank_constants_match_the_geometry() {
+        for (rank, squares) in [
+            (0, Board::WHITE_BACK_RANK),
+            (1, Board::WHITE_PAWN_RANK),
+            (6, Board::BLACK_PAWN_RANK),
+            (7, Board::BLACK_BACK_RANK),
+        ] {
+            for (file, &index) in squares.iter().enumerate() {
+                assert_eq!(index, Board::square_index(file, rank));
+            }
+        }
+        let board = Board::default();
+        assert!(Board::WHITE_PAWN_RANK
+            .iter()
+            .all(|&index| board[index] == Piece::new_unchecked(Piece::WHITE, Piece::PAWN)));
+        assert!(Board::BLACK_PAWN_RANK
+            .iter()
+            .all(|&index| board[index] == Piece::new_unchecked(Piece::BLACK, Piece::PAWN)));
+    }
+
+    #[test]
+    fn double_advances_set_the_en_passant_target() {
+        let mut board = Board::default();
+        assert_eq!(board.en_passant_target(), None);
+        board.make_move(uci(&board, "e2e4"));
+        assert_eq!(
+            board.en_passant_target(),
+            Board::square_from_name("e3").ok()
+        );
+        board.make_move(uci(&board, "d7d5"));
+        assert_eq!(
+            board.en_passant_target(),
+            Board::square_from_name("d6").ok()
+        );
+        board.make_move(uci(&board, "g1f3"));
+        assert_eq!(board.en_passant_target(), None);
+        board.make_move(uci(&board, "e7e6"));
+        assert_eq!(board.en_passant_target(), None);
+    }
+
+    #[test]
+    fn promotions_replace_the_pawn() {
+        let board = fen::parse_board("3r3k/4P3/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let mut after = board.clone();
+        let mv = Move::new_promotion(
+            Board::square_from_name("e7").unwrap(),
+            Board::square_from_name("d8").unwrap(),
+            Piece::KNIGHT,
+        );
+        let undo = after.make_move(mv);
+        assert_eq!(to_fen(&after), "3N3k/8/8/8/8/8/8/K7 b - - 0 1");
+        after.unmake_move(mv, undo);
+        assert_eq!(after, board);
+        assert_eq!(uci(&board, "e7e8q").to_uci(), "e7e8q");
+    }
+
+    #[test]
+    fn pieces_display_as_glyphs() {
+        assert_eq!(
+            Piece::new_unchecked(Piece::WHITE, Piece::KING).to_string(),
+            "♔"
+        );
+        assert_eq!(
+            Piece::new_unchecked(Piece::WHITE, Piece::KNIGHT).to_string(),
+            "♘"
+        );
+        assert_eq!(
+            Piece::new_unchecked(Piece::BLACK, Piece::PAWN).to_string(),
+            "♟"
+        );
+        assert_eq!(
+            Piece::new_unchecked(Piece::BLACK, Piece::QUEEN).to_string(),
+            "♛"
+        );
+        assert_eq!(Piece::NO_PIECE.to_string(), " ");
+    }
+
+    #[test]
+    fn render_styles() {
+        let board = Board::default();
+        let ascii = board.render(RenderStyle::Ascii, false);
+        assert!(
+            ascii.starts_with("8  r  n  b  q  k  b  n  r \n7  p"),
+            "{ascii}"
+        );
+        assert!(ascii.ends_with("1  R  N  B  Q  K  B  N  R \n   a  b  c  d  e  f  g  h \n"));
+        let unicode = board.render(RenderStyle::Unicode, false);
+        assert!(
+            unicode.starts_with("8  ♜  ♞  ♝  ♛  ♚  ♝  ♞  ♜ \n"),
+            "{unicode}"
+        );
+        assert!(unicode.contains("\n4  .  .  .  .  .  .  .  . \n"));
+        assert_eq!(board.to_string(), ascii);
+    }
+
+    #[test]
+    fn render_flipped_from_blacks_side() {
+        let flipped = Board::default().render(RenderStyle::Ascii, true);
+        assert!(
+            flipped.starts_with("1  R  N  B  K  Q  B  N  R \n2  P"),
+            "{flipped}"
+        );
+        assert!(flipped.ends_with("   h  g  f  e  d  c  b  a \n"));
+        // h1 is in the top left and a8 in the bottom right
+        let corners = fen::parse_board("k7/8/8/8/8/8/8/7K w - - 0 1").unwrap();
+        let flipped = corners.render(RenderStyle::Ascii, true);
+        assert!(flipped.starts_with("1  K  ."), "{flipped}");
+        assert!(flipped.contains(".  k \n   h"));
+    }
+
+    #[test]
+    fn boards_compare_by_position_and_state() {
+        assert_eq!(
+            Board::default(),
+            fen::parse_board(fen::STARTING_FEN).unwrap()
+        );
+        for other in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 2",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQk - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w Qkq - 0 1",
+        ] {
+            assert_ne!(
+                Board::default(),
+                fen::parse_board(other).unwrap(),
+                "{other}"
+            );
+        }
+        // the history doesn't count: the same position reached by moves is equal
+        let mut moved = Board::default();
+        moved
+            .apply_uci_moves(&["g1f3", "g8f6", "f3g1", "f6g8"])
+            .unwrap();
+        assert_eq!(
+            moved,
+            fen::parse_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 3").unwrap()
+        );
+    }
+
+    #[test]
+    fn fifty_moves_without_progress_are_a_draw() {
+        let mut board = fen::parse_board("4k3/8/8/8/8/8/4P3/1N2K1n1 w - - 0 1").unwrap();
+        for ply in 0..100 {
+            assert!(!board.is_fifty_move_draw());
+            let mv = ["b1c3", "g1h3", "c3b1", "h3g1"][ply % 4];
+            board.make_move(uci(&board, mv));
+        }
+        assert_eq!(board.halfmove_clock(), 100);
+        assert!(board.is_fifty_move_draw());
+        assert!(fen::parse_board(&to_fen(&board))
+            .unwrap()
+            .is_fifty_move_draw());
+        // a pawn move starts the count over
+        board.make_move(uci(&board, "e2e4"));
+        assert_eq!(board.halfmove_clock(), 0);
+        assert!(!board.is_fifty_move_draw());
+    }
+
+    #[test]
+    fn piece_at_the_start() {
+        let board = Board::default();
+        assert_eq!(
+            board.piece_at(Board::square_from_name("d1").unwrap()),
+            Some(Piece::new_unchecked(Piece::WHITE, Piece::QUEEN))
+        );
+        assert_eq!(board.piece_at(Board::square_from_name("d4").unwrap()), None);
+        assert_eq!(board.piece_at(Board::NUM_SQUARES), None);
+    }
+
+    #[test]
+    fn set_and_clear_squares() {
+        let mut board = Board::default();
+        let d4 = Board::square_from_name("d4").unwrap();
+        let queen = Piece::new_unchecked(Piece::BLACK, Piece::QUEEN);
+        assert_eq!(board.piece_at(d4), None);
+        board.set_piece(d4, queen);
+        assert_eq!(board.piece_at(d4), Some(queen));
+        assert_eq!(
+            board.bitboard(Color::Black, PieceKind::Queen).count_ones(),
+            2
+        );
+        assert_eq!(board.zobrist_hash(), zobrist::hash(&board));
+        board.clear_square(d4);
+        assert_eq!(board.piece_at(d4), None);
+        assert_eq!(board, Board::default());
+        assert_eq!(board.zobrist_hash(), Board::default().zobrist_hash());
+    }
+
+    #[test]
+    fn empty_board_has_no_pieces() {
+        let board = Board::empty();
+        assert_eq!(board.occupied().count(), 0);
+        assert!(board.gen_pseudo_legal(Color::White).is_empty());
+        assert!(board.gen_pseudo_legal(Color::Black).is_empty());
+        assert_eq!(board.turn(), Color::White);
+        assert_eq!(board.castle_rights(), 0);
+    }
+
+    #[test]
+    fn occupied_squares_of_the_start() {
+        let board = Board::default();
+        let occupied: Vec<(usize, Piece)> = board.occupied().collect();
+        assert_eq!(occupied.len(), 32);
+        for (index, piece) in occupied {
+            let color = if index < 16 {
+                Piece::BLACK
+            } else {
+                Piece::WHITE
+            };
+            assert_eq!(piece.color(), color, "{}", Board::square_name(index));
+            assert!(!(16..48).contains(&index));
+        }
+    }
+
+    #[test]
+    fn pieces_of_each_side() {
+        let board = Board::default();
+        assert_eq!(board.pieces_of(Color::White).count(), 16);
+        assert_eq!(board.pieces_of(Color::Black).count(), 16);
+        assert!(board
+            .pieces_of(Color::White)
+            .all(|(_, p)| p.is(Piece::WHITE)));
+        let after = fen::parse_board("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        let white: Vec<usize> = after.pieces_of(Color::White).map(|(i, _)| i).collect();
+        assert_eq!(white, [56, 60]);
+    }
+
+    #[test]
+    fn display_of_the_starting_position() {
+        // every square is three characters wide, the trailing space included
+        let expected = [
+            "8  r  n  b  q  k  b  n  r \n",
+            "7  p  p  p  p  p  p  p  p \n",
+            "6  .  .  .  .  .  .  .  . \n",
+            "5  .  .  .  .  .  .  .  . \n",
+            "4  .  .  .  .  .  .  .  . \n",
+            "3  .  .  .  .  .  .  .  . \n",
+            "2  P  P  P  P  P  P  P  P \n",
+            "1  R  N  B  Q  K  B  N  R \n",
+            "   a  b  c  d  e  f  g  h \n",
+        ]
+        .concat();
+        assert_eq!(Board::default().to_string(), expected);
+    }
+
+    #[test]
+    fn getters_read_the_fen_fields() {
+        let board = fen::parse_board("4k3/8/8/8/8/8/8/4K3 b - - 7 31").unwrap();
+        assert_eq!(board.turn(), Color::Black);
+        assert_eq!(board.halfmove_clock(), 7);
+        assert_eq!(board.fullmove_number(), 31);
+        assert_eq!(Board::default().turn(), Color::White);
+    }
+
+    #[test]
+    fn color_conversions() {
+        assert_eq!(Color::White.opposite(), Color::Black);
+        assert_eq!(Color::Black.opposite(), Color::White);
+        for color in [Color::White, Color::Black] {
+            assert_eq!(Color::from_bits(color.bits()), Some(color));
+            assert_eq!(u16::from(color), color.bits());
+        }
+        assert_eq!(Color::White.bits(), Piece::WHITE);
+        assert_eq!(Color::from_bits(Piece::WHITE | Piece::BLACK), None);
+        assert_eq!(Color::from_bits(Piece::PAWN), None);
+    }
+
+    #[test]
+    fn piece_kinds_map_to_the_bits() {
+        let bits = [
+            Piece::PAWN,
+            Piece::KNIGHT,
+            Piece::BISHOP,
+            Piece::ROOK,
+            Piece::QUEEN,
+            Piece::KING,
+        ];
+        for (kind, bit) in PieceKind::ALL.into_iter().zip(bits) {
+            assert_eq!(kind.to_bits(), bit);
+            assert_eq!(PieceKind::from_bits(bit), Some(kind));
+            assert_eq!(
+                Piece::new_unchecked(Piece::BLACK, bit).piece_kind(),
+                Some(kind)
+            );
+        }
+        assert_eq!(PieceKind::from_bits(Piece::WHITE), None);
+        assert_eq!(PieceKind::from_bits(Piece::ROOK | Piece::BISHOP), None);
+        assert_eq!(Board::default()[0].piece_kind(), Some(PieceKind::Rook));
+    }
+
+    #[test]
+    fn bitboards_match_the_pieces() {
+        let mut board =
+            fen::parse_board("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1")
+                .unwrap();
+        for ply in 0..60 {
+            for color in [Color::White, Color::Black] {
+                let mut occupancy = 0;
+                for kind in PieceKind::ALL {
+                    let expected = (0..Board::NUM_SQUARES)
+                        .filter(|&i| {
+                            board[i].color() == color.bits() && board[i].piece_kind() == Some(kind)
+                        })
+                        .fold(0u64, |bits, i| bits | 1 << i);
+                    assert_eq!(board.bitboard(color, kind), expected, "{}", to_fen(&board));
+                    occupancy |= expected;
+                }
+                assert_eq!(board.occupancy(color), occupancy);
+            }
+            let moves = board.gen_legal(board.turn());
+            if moves.is_empty() {
+                break;
+            }
+            board.make_move(moves[ply * 7 % moves.len()]);
+        }
+    }
+
+    #[test]
+    fn default_is_the_starting_position() {
+        assert_eq!(
+            Board::default(),
+            fen::parse_board(fen::STARTING_FEN).unwrap()
+        );
+        assert_eq!(to_fen(&Board::default()), fen::STARTING_FEN);
+    }
+
+    #[test]
+    fn with_move_leaves_the_board_alone() {
+        let board = Board::default();
+        let mv = uci(&board, "e2e4");
+        let after = board.with_move(mv);
+        assert_eq!(board, Board::default());
+        assert_eq!(board.zobrist_hash(), Board::default().zobrist_hash());
+        let mut played = board.clone();
+        played.make_move(mv);
+        assert_eq!(after, played);
+        assert_eq!(after.zobrist_hash(), played.zobrist_hash());
+    }
+
+    #[test]
+    fn unmaking_drops_the_position_from_the_history() {
+        let mut board = Board::default();
+        let mut played = Vec::new();
+        for mv in [
+            "g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1", "f6g8",
+        ] {
+            let mv = uci(&board, mv);
+            played.push((mv, board.make_move(mv)));
+        }
+        assert!(board.is_threefold_repetition());
+        let (mv, undo) = played.pop().unwrap();
+        board.unmake_move(mv, undo);
+        assert!(!board.is_threefold_repetition());
+        assert!(board.is_repetition());
+        while let Some((mv, undo)) = played.pop() {
+            board.unmake_move(mv, undo);
+        }
+        assert!(!board.is_repetition());
+    }
+
+    #[test]
+    fn null_moves_unmake_exactly() {
+        let mut board =
+            fen::parse_board("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 4 3")
+                .unwrap();
+        let start = board.clone();
+        let undo = board.make_null_move();
+        assert_eq!(board.turn(), Color::Black);
+        assert_eq!(board.en_passant_target(), None);
+        assert_eq!(board.zobrist_hash(), zobrist::hash(&board));
+        board.unmake_null_move(undo);
+        assert_eq!(board, start);
+        assert_eq!(board.zobrist_hash(), start.zobrist_hash());
+        assert_eq!(to_fen(&board), to_fen(&start));
+    }
+
+    #[test]
+    fn king_squares() {
+        let board = Board::default();
+        assert_eq!(
+            board.king_square(Color::White),
+            Board::square_from_name("e1").ok()
+        );
+        assert_eq!(
+            board.king_square(Color::Black),
+            Board::square_from_name("e8").ok()
+        );
+        assert_eq!(Board::empty().king_square(Color::White), None);
+    }
+
+    #[test]
+    fn apply_uci_moves_plays_an_opening() {
+        let mut board = Board::default();
+        board
+            .apply_uci_moves(&["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"])
+            .unwrap();
+        assert_eq!(
+            to_fen(&board),
+            "r1bqkbnr/pppp1ppp/2n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3"
+        );
+        let mut board = Board::default();
+        let error = board
+            .apply_uci_moves(&["e2e4", "e7e5", "e1e3"])
+            .unwrap_err();
+        assert!(error.contains("e1e3"), "{error}");
+        // the moves before the illegal one stay played
+        assert_eq!(board.fullmove_number(), 2);
+    }
+
+    #[test]
+    fn capturing_a_rook_at_home_takes_its_castle_right() {
+        let mut board = crate::fen::parse_board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let mv = Move::from_uci("h1h8", &board).unwrap();
+        board.make_move(mv);
+        assert_eq!(to_fen(&board), "r3k2R/8/8/8/8/8/8/R3K3 b Qq - 0 1");
+        assert!(!board.gen_legal(Color::Black).contains(&Move::Castle {
+            ty: CastleType::BlackShort
+        }));
+    }
+
+    #[test]
+    fn repetition_keys_ignore_the_clocks() {
+        let key = |fen| crate::fen::parse_board(fen).unwrap().repetition_key();
+        assert_eq!(
+            key("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1"),
+            key("4k3/8/8/8/3pP3/8/8/4K3 b - e3 7 40")
+        );
+        assert_ne!(
+            key("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1"),
+            key("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1")
+        );
+        // without a pawn to take it, the en passant target doesn't matter
+        assert_eq!(
+            key("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1"),
+            key("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1")
+        );
+    }
+
+    #[test]
+    fn pretty_print_marks_the_last_move() {
+        let mut board = Board::default();
+        let mv = Move::from_uci("e2e4", &board).unwrap();
+        board.make_move(mv);
+        let printed = board.pretty_print(Some(mv));
+        let lines: Vec<&str> = printed.lines().collect();
+        assert_eq!(lines[4], "4  .  .  .  . [P] .  .  . ");
+        assert_eq!(lines[6], "2  P  P  P  P [.] P  P  P ");
+        assert_eq!(printed.matches('[').count(), 2);
+        assert_eq!(board.pretty_print(None), board.to_string());
+        // castles mark the king's squares
+        let mut board = crate::fen::parse_board("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let castle = Move::new_castle(CastleType::WhiteShort);
+        board.make_move(castle);
+        let printed = board.pretty_print(Some(castle));
+        assert_eq!(printed.lines().nth(7), Some("1  .  .  .  . [.] R [K] . "));
+    }
+
+    #[test]
+    fn material_of_the_start() {
+        let material = Board::default().material();
+        for color in [Color::White, Color::Black] {
+            let count = |kind| material.count(color, kind);
+            assert_eq!(count(PieceKind::Pawn), 8);
+            assert_eq!(count(PieceKind::Knight), 2);
+            assert_eq!(count(PieceKind::Bishop), 2);
+            assert_eq!(count(PieceKind::Rook), 2);
+            assert_eq!(count(PieceKind::Queen), 1);
+            assert_eq!(count(PieceKind::King), 1);
+            assert_eq!(material.total(color), 16);
+        }
+        let material = crate::fen::parse_board("4k3/8/8/8/8/8/8/QQ2K3 w - - 0 1")
+            .unwrap()
+            .material();
+        assert_eq!(material.count(Color::White, PieceKind::Queen), 2);
+        assert_eq!(material.count(Color::Black, PieceKind::Queen), 0);
+        assert_eq!(material.total(Color::Black), 1);
+    }
+
+    #[test]
+    fn with_color_and_with_kind() {
+        let white_pawn = Piece::new_unchecked(Piece::WHITE, Piece::PAWN);
+        let black_pawn = white_pawn.with_color(Piece::BLACK);
+        assert_eq!(black_pawn, Piece::new_unchecked(Piece::BLACK, Piece::PAWN));
+        assert!(!black_pawn.is(Piece::WHITE));
+        assert_eq!(black_pawn.with_color(Piece::WHITE), white_pawn);
+        assert_eq!(
+            white_pawn.with_kind(Piece::QUEEN),
+            Piece::new_unchecked(Piece::WHITE, Piece::QUEEN)
+        );
+        assert_eq!(black_pawn.with_kind(Piece::QUEEN).color(), Piece::BLACK);
+        assert_eq!(Piece::NO_PIECE.with_kind(Piece::QUEEN), Piece::NO_PIECE);
+        assert_eq!(Piece::NO_PIECE.with_color(Piece::BLACK), Piece::NO_PIECE);
+    }
+
+    #[test]
+    fn boards_from_their_squares() {
+        let squares = *Board::default().squares();
+        assert_eq!(squares[0], Piece::new_unchecked(Piece::BLACK, Piece::ROOK));
+        assert_eq!(squares[60], Piece::new_unchecked(Piece::WHITE, Piece::KING));
+        assert_eq!(squares[35], Piece::NO_PIECE);
+        let board = Board::from_squares(squares, Color::Black).unwrap();
+        assert_eq!(board.squares(), &squares);
+        // without castle rights, en passant target or clocks
+        assert_eq!(
+            to_fen(&board),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b - - 0 1"
+        );
+        let mut no_king = squares;
+        no_king[4] = Piece::NO_PIECE;
+        assert_eq!(
+            Board::from_squares(no_king, Color::White).unwrap_err(),
+            "black has to have exactly one king, found 0"
+        );
+        let mut two_kings = squares;
+        two_kings[35] = Piece::new_unchecked(Piece::WHITE, Piece::KING);
+        assert!(Board::from_squares(two_kings, Color::White).is_err());
+    }
+
+    #[test]
+    fn builder_sets_up_the_starting_position() {
+        // every piece of `board` and every castle right, whether `board` has it or not
+        let rebuild = |board: &Board, builder: BoardBuilder| {
+            let builder = board
+                .occupied()
+                .fold(builder, |builder, (square, p)| builder.piece(square, p));
+            CastleType::all()
+                .into_iter()
+                .fold(builder, BoardBuilder::castle)
+                .build()
+        };
+        let start = crate::fen::parse_board(crate::fen::STARTING_FEN).unwrap();
+        let built = rebuild(&start, Board::builder());
+        assert_eq!(built, start);
+        assert_eq!(to_fen(&built), crate::fen::STARTING_FEN);
+        let after_e4 =
+            crate::fen::parse_board("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+                .unwrap();
+        let built = rebuild(
+            &after_e4,
+            Board::builder().turn(Color::Black).en_passant(44),
+        );
+        assert_eq!(to_fen(&built), to_fen(&after_e4));
+    }
+
+    #[test]
+    #[should_panic(expected = "can't build the board")]
+    fn build_panics_without_kings() {
+        Board::builder().build();
+    }
+}
@@ -1,4 +1,8 @@
-use crate::chess::{Board, Piece};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::chess::{Board, Color, Piece};
 use crate::r#move::CastleType;
 
 pub const FEN_WHITE: char = 'w';
@@ -16,33 +20,268 @@ pub const FEN_BLACK_BISHOP: char = 'b';
 pub const FEN_BLACK_ROOK: char = 'r';
 pub const FEN_BLACK_QUEEN: char = 'q';
 pub const FEN_BLACK_KING: char = 'k';
+/// the most empty squares one digit of the position field stands for, a whole rank
+const MAX_EMPTY_SQUARES: char = (b'0' + Board::NUM_FILES as u8) as char;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// what's wrong with a fen or epd string
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+    /// the named field is missing
+    MissingField(&'static str),
+    /// an unexpected character in the position field
+    BadPiece(char),
+    /// the given rank (8 to 1) describes more than 8 files
+    RankOverflow(usize),
+    /// a rank delimiter or the end of the field after `files` of the 8 files of `rank`
+    IncompleteRank {
+        rank: usize,
+        files: usize,
+    },
+    /// the position field describes more than 8 ranks
+    TooManyRanks,
+    /// the position field ends this many ranks too early
+    MissingRanks(usize),
+    /// `color` ("white" or "black") doesn't have exactly one king but `count`
+    KingCount {
+        color: &'static str,
+        count: usize,
+    },
+    /// a pawn on the named square on the first or eighth rank,
+    /// which it can neither start on nor stay on after promoting
+    PawnOnBackRank(String),
+    BadTurn(String),
+    BadCastleRight(char),
+    /// a castle right for a side and wing that already has one
+    DuplicateCastleRight(char),
+    /// a castle right without its king and, on the castling side of it, its rook on the
+    /// back rank, only in strict validation
+    NoCastlePieces(char),
+    BadEnPassant(String),
+    /// the halfmove clock or fullmove number field `field` isn't a number
+    BadMoveNumber {
+        name: &'static str,
+        field: String,
+    },
+    /// an epd operation operand with an opening but no closing quote
+    UnterminatedString,
+    DuplicateOperation(String),
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Self::MissingField(name) => write!(f, "fen string expected to have a {name} field"),
+            Self::BadPiece(c) => write!(f, "unexpected character '{c}' in position field"),
+            Self::RankOverflow(rank) => {
+                write!(
+                    f,
+                    "rank {rank} in the position field has more than {} files",
+                    Board::NUM_FILES
+                )
+            }
+            Self::IncompleteRank { rank, files } => write!(
+                f,
+                "rank {rank} in the position field has only {files} of {} files",
+                Board::NUM_FILES
+            ),
+            Self::TooManyRanks => write!(
+                f,
+                "position field describes more than {} ranks",
+                Board::NUM_RANKS
+            ),
+            Self::MissingRanks(n) => write!(f, "position field is missing the last {n} ranks"),
+            Self::KingCount { color, count } => {
+                write!(f, "{color} has to have exactly one king, found {count}")
+            }
+            Self::PawnOnBackRank(square) => {
+                write!(f, "pawn on {square} can't be on the first or eighth rank")
+            }
+            Self::BadTurn(field) => write!(
+                f,
+                "illegal second (turn) field '{field}'; one of {FEN_WHITE} or {FEN_BLACK} expected"
+            ),
+            Self::BadCastleRight(c) => {
+                write!(f, "unexpected character '{c}' in castle rights field")
+            }
+            Self::DuplicateCastleRight(c) => write!(
+                f,
+                "castle right '{c}' contradicts an earlier one of the same side"
+            ),
+            Self::NoCastlePieces(c) => {
+                write!(f, "no king and rook to castle with for castle right '{c}'")
+            }
+            Self::BadEnPassant(field) => write!(
+                f,
+                "illegal en passant field '{field}'; a square on rank 6 with white to move \
+                 or rank 3 with black to move expected"
+            ),
+            Self::BadMoveNumber { name, field } => {
+                write!(f, "illegal {name} field '{field}'; number expected")
+            }
+            Self::UnterminatedString => write!(f, "unterminated string in epd operations"),
+            Self::DuplicateOperation(opcode) => {
+                write!(f, "epd contains operation '{opcode}' twice")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
 
-pub const STARTING_FEN: &'static str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+impl From<FenError> for String {
+    fn from(e: FenError) -> Self {
+        e.to_string()
+    }
+}
+
+/// what to do with castle rights whose king or rook isn't on its square
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CastleValidation {
+    /// fail with `FenError::NoCastlePieces`
+    Strict,
+    /// drop the right and parse the rest of the fen
+    Lenient,
+}
 
 // only requires position and turn fields, default if the rest is missing
-pub fn parse_board(fen: &str) -> Result<Board, String> {
+pub fn parse_board(fen: &str) -> Result<Board, FenError> {
+    parse_board_with(fen, CastleValidation::Lenient)
+}
+
+// `parse_board`, but a bare position field is enough: white moves unless told otherwise.
+// the other defaults are `parse_board`'s, every castle right the pieces allow,
+// no en passant target and the clocks of a new game
+pub fn parse_board_lenient(fen: &str) -> Result<Board, FenError> {
+    match fen.split_whitespace().count() {
+        1 => parse_board(&format!("{} {FEN_WHITE}", fen.trim())),
+        _ => parse_board(fen),
+    }
+}
+
+pub fn parse_board_with(fen: &str, validation: CastleValidation) -> Result<Board, FenError> {
     // would only need to split at spaces per definition but what gives
     let mut fields = fen.split_whitespace();
-    let pos_field = fields
-        .next()
-        .ok_or_else(|| String::from("non-empty fen string expected"))?;
-    let turn_field = fields
-        .next()
-        .ok_or_else(|| String::from("fen string expected to have at least first two fields"))?;
+    let pos_field = fields.next().ok_or(FenError::MissingField("position"))?;
+    let turn_field = fields.next().ok_or(FenError::MissingField("turn"))?;
     let castle_field = fields.next();
+    let en_passant_field = fields.next();
+    let halfmove_field = fields.next();
+    let fullmove_field = fields.next();
 
     let pieces = parse_position(pos_field)?;
+    validate_position(&pieces)?;
     let turn = parse_turn_field(turn_field)?;
-    let castle_rights = parse_castle_field(castle_field)?;
+    let (castle_rights, castle_rook_files, castle_king_files) =
+        parse_castle_field(castle_field, &pieces, validation)?;
+    let en_passant_target = parse_en_passant_field(en_passant_field, turn)?;
+    let halfmove_clock = parse_move_number_field(halfmove_field, 0, "halfmove clock")?;
+    let fullmove_number = parse_move_number_field(fullmove_field, 1, "fullmove number")?;
+
+    Ok(Board::new(
+        pieces,
+        turn,
+        castle_rights,
+        en_passant_target,
+        halfmove_clock,
+        fullmove_number,
+    )
+    .with_castle_rook_files(castle_rook_files)
+    .with_castle_king_files(castle_king_files))
+}
+
+/// `parse_board`, so `"8/8/...".parse::<Board>()` works
+impl FromStr for Board {
+    type Err = FenError;
+
+    fn from_str(fen: &str) -> Result<Self, FenError> {
+        parse_board(fen)
+    }
+}
+
+// an epd is the first four fen fields followed by operations like `bm Nf3; id "WAC.001";`.
+// the operands are kept as written, only the quotes around a single string are removed
+pub fn parse_epd(epd: &str) -> Result<(Board, HashMap<String, String>), FenError> {
+    let mut rest = epd.trim();
+    let mut fields = Vec::new();
+    for name in ["position", "turn", "castle rights", "en passant"] {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if end == 0 {
+            return Err(FenError::MissingField(name));
+        }
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+
+    let pieces = parse_position(fields[0])?;
+    validate_position(&pieces)?;
+    let turn = parse_turn_field(fields[1])?;
+    let (castle_rights, castle_rook_files, castle_king_files) =
+        parse_castle_field(Some(fields[2]), &pieces, CastleValidation::Lenient)?;
+    let en_passant_target = parse_en_passant_field(Some(fields[3]), turn)?;
 
-    Ok(Board::new(pieces, turn, castle_rights))
+    let mut operations = HashMap::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                parse_epd_operation(&rest[start..i], &mut operations)?;
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if in_quotes {
+        return Err(FenError::UnterminatedString);
+    }
+    parse_epd_operation(&rest[start..], &mut operations)?;
+
+    let board = Board::new(pieces, turn, castle_rights, en_passant_target, 0, 1)
+        .with_castle_rook_files(castle_rook_files)
+        .with_castle_king_files(castle_king_files);
+    Ok((board, operations))
+}
+
+fn parse_epd_operation(
+    operation: &str,
+    operations: &mut HashMap<String, String>,
+) -> Result<(), FenError> {
+    let operation = operation.trim();
+    if operation.is_empty() {
+        return Ok(());
+    }
+    let (opcode, operand) = operation
+        .split_once(char::is_whitespace)
+        .map_or((operation, ""), |(opcode, operand)| {
+            (opcode, operand.trim())
+        });
+    let operand = operand
+        .strip_prefix('"')
+        .and_then(|o| o.strip_suffix('"'))
+        .filter(|o| !o.contains('"'))
+        .unwrap_or(operand);
+    if operations
+        .insert(opcode.to_string(), operand.to_string())
+        .is_some()
+    {
+        return Err(FenError::DuplicateOperation(opcode.to_string()));
+    }
+    Ok(())
 }
 
-fn parse_position(field: &str) -> Result<[Piece; 64], String> {
-    let mut pieces = [Piece::from(Piece::NONE); 64];
-    let mut index: u32 = 0;
+// ranks are described from the last down to the first, each of them exactly `NUM_FILES` wide
+fn parse_position(field: &str) -> Result<[Piece; Board::NUM_SQUARES], FenError> {
+    let mut pieces = [Piece::NO_PIECE; Board::NUM_SQUARES];
+    let files = Board::NUM_FILES as usize;
+    let ranks = Board::NUM_RANKS as usize;
+    // the rank being described, counted from the top, and how many of its files are done
+    let mut rank = 0;
+    let mut file = 0;
     for c in field.chars() {
-        pieces[index as usize] = Piece::from(match c {
+        let piece = Piece::from(match c {
             FEN_WHITE_PAWN => Piece::WHITE | Piece::PAWN,
             FEN_WHITE_KNIGHT => Piece::WHITE | Piece::KNIGHT,
             FEN_WHITE_BISHOP => Piece::WHITE | Piece::BISHOP,
@@ -55,93 +294,271 @@ fn parse_position(field: &str) -> Result<[Piece; 64], String> {
             FEN_BLACK_ROOK => Piece::BLACK | Piece::ROOK,
             FEN_BLACK_QUEEN => Piece::BLACK | Piece::QUEEN,
             FEN_BLACK_KING => Piece::BLACK | Piece::KING,
-            d if d.is_digit(Board::NUM_FILES + 1) => {
-                let n = c.to_digit(Board::NUM_FILES + 1).unwrap();
-                if n > Board::NUM_FILES - index % Board::NUM_FILES {
-                    return Err(format!(
-                        "there aren't {n} empty fields left in the current rank!"
-                    ));
-                } else {
-                    index += n;
-                    continue;
+            '1'..=MAX_EMPTY_SQUARES => {
+                file += c as usize - '0' as usize;
+                if file > files {
+                    return Err(FenError::RankOverflow(ranks - rank));
                 }
+                continue;
             }
             FEN_NEW_RANK => {
-                if index % 8 != 0 {
-                    return Err(format!("rank not yet done at rank delimiter '{c}'"));
+                if file != files {
+                    return Err(FenError::IncompleteRank {
+                        rank: ranks - rank,
+                        files: file,
+                    });
+                }
+                rank += 1;
+                file = 0;
+                if rank == ranks {
+                    return Err(FenError::TooManyRanks);
                 }
                 continue;
             }
-            _ => return Err(format!("unexpected character '{c}' in position field!")),
+            _ => return Err(FenError::BadPiece(c)),
         });
-        index += 1;
+        if file == files {
+            return Err(FenError::RankOverflow(ranks - rank));
+        }
+        pieces[rank * files + file] = piece;
+        file += 1;
+    }
+    if file != files {
+        return Err(FenError::IncompleteRank {
+            rank: ranks - rank,
+            files: file,
+        });
+    }
+    if rank != ranks - 1 {
+        return Err(FenError::MissingRanks(ranks - 1 - rank));
     }
     Ok(pieces)
 }
 
-fn parse_turn_field(field: &str) -> Result<u16, String> {
-    if field.len() != 1 {
-        return Err(format!(
-            "illegal second (turn) field '{field}'; one of {} or {} expected",
-            FEN_WHITE, FEN_BLACK
-        ));
-    }
-    Ok(match field.chars().next().unwrap() {
-        FEN_WHITE => Piece::WHITE,
-        FEN_BLACK => Piece::BLACK,
-        _ => {
-            return Err(format!(
-                "expected '{}' or '{}' as second field!",
-                FEN_WHITE, FEN_BLACK
-            ))
+/// checks that each side has exactly one king, which move generation and check detection
+/// rely on, and that no pawn stands where it can't move forwards from
+pub fn validate_position(pieces: &[Piece; Board::NUM_SQUARES]) -> Result<(), FenError> {
+    for (color, name) in [(Piece::WHITE, "white"), (Piece::BLACK, "black")] {
+        let kings = pieces
+            .iter()
+            .filter(|p| p.color() == color && p.kind() == Piece::KING)
+            .count();
+        if kings != 1 {
+            return Err(FenError::KingCount {
+                color: name,
+                count: kings,
+            });
         }
-    })
+    }
+    let mut back_ranks = Board::WHITE_BACK_RANK.iter().chain(&Board::BLACK_BACK_RANK);
+    if let Some(&square) = back_ranks.find(|&&square| pieces[square].kind() == Piece::PAWN) {
+        return Err(FenError::PawnOnBackRank(Board::square_name(square)));
+    }
+    Ok(())
 }
 
-fn parse_castle_field(field: Option<&str>) -> Result<u8, String> {
-    if field.is_none() {
-        return Ok(CastleType::BIT_BLACK_LONG
-            | CastleType::BIT_BLACK_SHORT
-            | CastleType::BIT_WHITE_LONG
-            | CastleType::BIT_WHITE_SHORT);
-    }
-    let field = field.unwrap();
-    let mut chars = field.chars().peekable();
-    if field.len() == 1 && *chars.peek().unwrap() == '-' {
-        return Ok(0);
+fn parse_turn_field(field: &str) -> Result<Color, FenError> {
+    let mut chars = field.chars();
+    match (chars.next(), chars.next()) {
+        (Some(FEN_WHITE), None) => Ok(Color::White),
+        (Some(FEN_BLACK), None) => Ok(Color::Black),
+        _ => Err(FenError::BadTurn(field.to_string())),
     }
+}
+
+// besides the classic KQkq, the files of the castling rooks are understood for Chess960
+// (Shredder-FEN, e.g. HAha). as in X-FEN, K and Q then stand for the outermost rook.
+// a right only stands if the king is on its back rank, on the e-file in classic chess
+// but anywhere in Chess960, with the rook on the castling side. the king's files are
+// returned along with the rooks', indexed by `Color as usize`
+fn parse_castle_field(
+    field: Option<&str>,
+    pieces: &[Piece; Board::NUM_SQUARES],
+    validation: CastleValidation,
+) -> Result<(u8, [usize; 4], [usize; 2]), FenError> {
+    let mut rook_files = Board::CLASSIC_CASTLE_ROOK_FILES;
+    let mut king_files = Board::CLASSIC_CASTLE_KING_FILES;
+    let (field, validation) = match field {
+        // the default rights are dropped quietly where they can't apply
+        None => ("KQkq", CastleValidation::Lenient),
+        Some("-") => return Ok((0, rook_files, king_files)),
+        Some(field) => (field, validation),
+    };
     let mut result = 0;
-    macro_rules! add_once {
-        ($bit:path, $c:ident) => {{
-            if result & $bit == $bit {
-                Err(format!("fen contains castle right '{}' twice!", $c))
-            } else {
-                result = result | $bit;
-                Ok(())
+    let mut seen = 0;
+    for c in field.chars() {
+        let (color, rank) = if c.is_ascii_uppercase() {
+            (Piece::WHITE, 0)
+        } else {
+            (Piece::BLACK, Board::NUM_RANKS as usize - 1)
+        };
+        let back_rank = |file: usize| pieces[Board::square_index(file, rank)];
+        let king_file = (0..Board::NUM_FILES as usize)
+            .find(|&f| back_rank(f) == Piece::new_unchecked(color, Piece::KING));
+        let rook = Piece::new_unchecked(color, Piece::ROOK);
+        let (short, file) = match c {
+            FEN_WHITE_KING | FEN_BLACK_KING => {
+                let file = king_file
+                    .and_then(|k| {
+                        (k + 1..Board::NUM_FILES as usize)
+                            .rev()
+                            .find(|&f| back_rank(f) == rook)
+                    })
+                    .unwrap_or(Board::NUM_FILES as usize - 1);
+                (true, file)
+            }
+            FEN_WHITE_QUEEN | FEN_BLACK_QUEEN => {
+                let file = king_file
+                    .and_then(|k| (0..k).find(|&f| back_rank(f) == rook))
+                    .unwrap_or(0);
+                (false, file)
+            }
+            'A'..='H' | 'a'..='h' => {
+                let file = c.to_ascii_lowercase() as usize - 'a' as usize;
+                // without the king there is no telling which side the rook is on
+                (king_file.is_some_and(|k| file > k), file)
             }
-        }};
+            _ => return Err(FenError::BadCastleRight(c)),
+        };
+        let ty = match (color == Piece::WHITE, short) {
+            (true, true) => CastleType::WhiteShort,
+            (true, false) => CastleType::WhiteLong,
+            (false, true) => CastleType::BlackShort,
+            (false, false) => CastleType::BlackLong,
+        };
+        if seen & ty.get_bit() != 0 {
+            return Err(FenError::DuplicateCastleRight(c));
+        }
+        seen |= ty.get_bit();
+        let in_place = king_file.is_some_and(|k| (file > k) == short) && back_rank(file) == rook;
+        let Some(king_file) = king_file.filter(|_| in_place) else {
+            match validation {
+                CastleValidation::Strict => return Err(FenError::NoCastlePieces(c)),
+                CastleValidation::Lenient => continue,
+            }
+        };
+        result |= ty.get_bit();
+        rook_files[ty as usize] = file;
+        king_files[ty.color() as usize] = king_file;
     }
-    for c in chars {
-        match c {
-            FEN_BLACK_QUEEN => add_once!(CastleType::BIT_BLACK_LONG, c)?,
-            FEN_BLACK_KING => add_once!(CastleType::BIT_BLACK_SHORT, c)?,
-            FEN_WHITE_QUEEN => add_once!(CastleType::BIT_WHITE_LONG, c)?,
-            FEN_WHITE_KING => add_once!(CastleType::BIT_WHITE_SHORT, c)?,
-            _ => return Err(format!("unexpected character '{c}' in castle rights field")),
+    Ok((result, rook_files, king_files))
+}
+
+// the target has to be behind a pawn that just double advanced, so on the third rank
+// from the top with white to move and on the third from the bottom with black to move
+fn parse_en_passant_field(field: Option<&str>, turn: Color) -> Result<Option<usize>, FenError> {
+    let field = match field {
+        None | Some("-") => return Ok(None),
+        Some(field) => field,
+    };
+    let expected_rank = if turn == Color::White {
+        Board::NUM_RANKS as usize - 3
+    } else {
+        2
+    };
+    match Board::square_from_name(field) {
+        Ok(square) if Board::rank_of(square) == expected_rank => Ok(Some(square)),
+        _ => Err(FenError::BadEnPassant(field.to_string())),
+    }
+}
+
+fn parse_move_number_field(
+    field: Option<&str>,
+    default: u16,
+    name: &'static str,
+) -> Result<u16, FenError> {
+    match field {
+        None => Ok(default),
+        Some(field) => field.parse().map_err(|_| FenError::BadMoveNumber {
+            name,
+            field: field.to_string(),
+        }),
+    }
+}
+
+pub fn to_fen(board: &Board) -> String {
+    let mut fen = String::new();
+    let mut empty = 0;
+    for i in 0..Board::NUM_SQUARES {
+        if i != 0 && i % Board::NUM_FILES as usize == 0 {
+            if empty != 0 {
+                fen.push_str(&empty.to_string());
+                empty = 0;
+            }
+            fen.push(FEN_NEW_RANK);
+        }
+        if board[i] == Piece::NO_PIECE {
+            empty += 1;
+        } else {
+            if empty != 0 {
+                fen.push_str(&empty.to_string());
+                empty = 0;
+            }
+            fen.push(fen_char(&board[i]));
+        }
+    }
+    if empty != 0 {
+        fen.push_str(&empty.to_string());
+    }
+
+    fen.push(' ');
+    fen.push(if board.turn() == Color::White {
+        FEN_WHITE
+    } else {
+        FEN_BLACK
+    });
+
+    fen.push(' ');
+    let castle_rights = board.castle_rights();
+    if castle_rights == 0 {
+        fen.push('-');
+    }
+    for (ty, c) in [
+        (CastleType::WhiteShort, FEN_WHITE_KING),
+        (CastleType::WhiteLong, FEN_WHITE_QUEEN),
+        (CastleType::BlackShort, FEN_BLACK_KING),
+        (CastleType::BlackLong, FEN_BLACK_QUEEN),
+    ] {
+        if castle_rights & ty.get_bit() == 0 {
+            continue;
         }
+        // rooks off the classic files are written as their file, Shredder-FEN style
+        let file = board.castle_rook_file(ty);
+        if file == Board::CLASSIC_CASTLE_ROOK_FILES[ty as usize] {
+            fen.push(c);
+        } else {
+            let file = (b'a' + file as u8) as char;
+            fen.push(if c.is_ascii_uppercase() {
+                file.to_ascii_uppercase()
+            } else {
+                file
+            });
+        }
+    }
+
+    fen.push(' ');
+    match board.en_passant_target() {
+        Some(square) => fen.push_str(&Board::square_name(square)),
+        None => fen.push('-'),
     }
-    Ok(result)
+
+    fen.push_str(&format!(
+        " {} {}",
+        board.halfmove_clock(),
+        board.fullmove_number()
+    ));
+    fen
 }
 
 pub fn fen_char(p: &Piece) -> char {
-    let mut piece = match p.inner() & !(Piece::WHITE | Piece::BLACK) /*"removes" the color bits*/ {
+    let mut piece = match p.kind() {
         Piece::PAWN => FEN_BLACK_PAWN,
         Piece::KNIGHT => FEN_BLACK_KNIGHT,
         Piece::BISHOP => FEN_BLACK_BISHOP,
         Piece::ROOK => FEN_BLACK_ROOK,
         Piece::QUEEN => FEN_BLACK_QUEEN,
         Piece::KING => FEN_BLACK_KING,
-        Piece::NONE => ' ',
+        0 => ' ',
         _ => panic!("double flagged piece {}", p.inner()),
     };
     if p.is(Piece::WHITE) {
@@ -149,3 +566,402 @@ pub fn fen_char(p: &Piece) -> char {
     }
     piece
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_fen_round_trips() {
+        assert_eq!(to_fen(&parse_board(STARTING_FEN).unwrap()), STARTING_FEN);
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40",
+        ] {
+            assert_eq!(to_fen(&parse_board(fen).unwrap()), fen);
+        }
+    }
+
+    #[test]
+    fn parses_chess960_castle_rights() {
+        let files = |fen| {
+            let board = parse_board_with(fen, CastleValidation::Strict).unwrap();
+            (
+                board.castle_rights(),
+                CastleType::all().map(|ty| board.castle_rook_file(ty)),
+            )
+        };
+        let all = CastleType::fold_bits(CastleType::all());
+        // CastleType::all() is black long, black short, white long, white short
+        assert_eq!(
+            files("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1"),
+            (all, [1, 6, 1, 6])
+        );
+        // K and Q stand for the outermost rooks
+        assert_eq!(
+            files("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1"),
+            (all, [1, 6, 1, 6])
+        );
+        let short = CastleType::WhiteShort.get_bit();
+        assert_eq!(
+            files("4k3/8/8/8/8/8/8/4KRR1 w K - 0 1"),
+            (short, [0, 7, 0, 6])
+        );
+        assert_eq!(
+            files("4k3/8/8/8/8/8/8/4KRR1 w F - 0 1"),
+            (short, [0, 7, 0, 5])
+        );
+        assert_eq!(files(STARTING_FEN), (all, Board::CLASSIC_CASTLE_ROOK_FILES));
+    }
+
+    #[test]
+    fn rejects_contradictory_castle_rights() {
+        let strict = |fen| parse_board_with(fen, CastleValidation::Strict).unwrap_err();
+        assert_eq!(
+            strict("4k3/8/8/8/8/8/8/4KRR1 w GK - 0 1"),
+            FenError::DuplicateCastleRight('K')
+        );
+        assert_eq!(
+            strict("4k3/8/8/8/8/8/8/R3K2R w HAA - 0 1"),
+            FenError::DuplicateCastleRight('A')
+        );
+        assert_eq!(
+            strict("4k3/8/8/8/8/8/8/R3K2R w B - 0 1"),
+            FenError::NoCastlePieces('B')
+        );
+        assert_eq!(
+            strict("4k3/8/8/8/8/8/8/R3K2R w Kx - 0 1"),
+            FenError::BadCastleRight('x')
+        );
+        // lenient parsing drops the right instead
+        let lenient = parse_board("4k3/8/8/8/8/8/8/R3K2R w BH - 0 1").unwrap();
+        assert_eq!(lenient.castle_rights(), CastleType::WhiteShort.get_bit());
+    }
+
+    #[test]
+    fn reports_what_is_wrong() {
+        let error = |fen: &str| parse_board(fen).unwrap_err();
+        assert_eq!(error(""), FenError::MissingField("position"));
+        assert_eq!(error("8/8/8/8/8/8/8/8"), FenError::MissingField("turn"));
+        assert_eq!(
+            error("rnbqkbnr/ppppXppp/8/8/8/8/PPPPPPPP/RNBQKBNR w"),
+            FenError::BadPiece('X')
+        );
+        assert_eq!(
+            error("rnbqkbnr/pppppppp/441/8/8/8/PPPPPPPP/RNBQKBNR w"),
+            FenError::RankOverflow(6)
+        );
+        assert_eq!(
+            error("rnbqkbnr/pppppppp/8/8/7/8/PPPPPPPP/RNBQKBNR w"),
+            FenError::IncompleteRank { rank: 4, files: 7 }
+        );
+        assert_eq!(
+            error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8 w"),
+            FenError::TooManyRanks
+        );
+        assert_eq!(
+            error("rnbqkbnr/pppppppp/8/8/8/8 w"),
+            FenError::MissingRanks(2)
+        );
+        assert_eq!(
+            error("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w"),
+            FenError::KingCount {
+                color: "black",
+                count: 0
+            }
+        );
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+        assert_eq!(
+            error(&format!("{start} x")),
+            FenError::BadTurn("x".to_string())
+        );
+        assert_eq!(
+            error(&format!("{start} w KK")),
+            FenError::DuplicateCastleRight('K')
+        );
+        assert_eq!(
+            error(&format!("{start} w KQkq e3")),
+            FenError::BadEnPassant("e3".to_string())
+        );
+        assert_eq!(
+            error(&format!("{start} w KQkq - x")),
+            FenError::BadMoveNumber {
+                name: "halfmove clock",
+                field: "x".to_string()
+            }
+        );
+        // the messages name the problem
+        assert_eq!(
+            FenError::MissingField("turn").to_string(),
+            "fen string expected to have a turn field"
+        );
+    }
+
+    #[test]
+    fn castle_rights_need_the_king_on_its_back_rank() {
+        let strict = |fen| parse_board_with(fen, CastleValidation::Strict);
+        let fen = "4k3/8/8/8/8/8/2K5/7R w K - 0 1";
+        assert_eq!(parse_board(fen).unwrap().castle_rights(), 0);
+        assert_eq!(strict(fen).unwrap_err(), FenError::NoCastlePieces('K'));
+        // the rook has to be on the castling side of the king
+        assert_eq!(
+            strict("4k3/8/8/8/8/8/8/R4K2 w H - 0 1").unwrap_err(),
+            FenError::NoCastlePieces('H')
+        );
+        // off the e-file, the king castles from wherever it stands
+        let board = strict("4k3/8/8/8/8/8/8/2K4R w K - 0 1").unwrap();
+        assert_eq!(board.castle_rights(), CastleType::WhiteShort.get_bit());
+        assert_eq!(board.castle_king_file(Color::White), 2);
+        let board = strict("4k3/8/8/8/8/8/8/R4K1R w HA - 0 1").unwrap();
+        assert_eq!(board.castle_king_square(CastleType::WhiteLong), 61);
+    }
+
+    #[test]
+    fn lenient_parsing_drops_rights_without_their_rook() {
+        let board = parse_board("r3k2r/8/8/8/8/8/8/R3K3 w KQkq - 0 1").unwrap();
+        let expected = CastleType::fold_bits([
+            CastleType::WhiteLong,
+            CastleType::BlackLong,
+            CastleType::BlackShort,
+        ]);
+        assert_eq!(board.castle_rights(), expected);
+        assert_eq!(
+            parse_board_with(
+                "r3k2r/8/8/8/8/8/8/R3K3 w KQkq - 0 1",
+                CastleValidation::Strict
+            )
+            .unwrap_err(),
+            FenError::NoCastlePieces('K')
+        );
+    }
+
+    #[test]
+    fn parses_the_en_passant_square() {
+        let board =
+            parse_board("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap();
+        assert_eq!(
+            board.en_passant_target(),
+            Board::square_from_name("f6").ok()
+        );
+        let board =
+            parse_board("rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 3").unwrap();
+        assert_eq!(
+            board.en_passant_target(),
+            Board::square_from_name("d3").ok()
+        );
+        assert_eq!(parse_board(STARTING_FEN).unwrap().en_passant_target(), None);
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+        assert_eq!(
+            parse_board(&format!("{start} w KQkq z9")).unwrap_err(),
+            FenError::BadEnPassant("z9".to_string())
+        );
+    }
+
+    #[test]
+    fn parses_the_move_counters() {
+        let board = parse_board("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40").unwrap();
+        assert_eq!((board.halfmove_clock(), board.fullmove_number()), (12, 40));
+        // missing counters default to a fresh game
+        let board = parse_board("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - -").unwrap();
+        assert_eq!((board.halfmove_clock(), board.fullmove_number()), (0, 1));
+        assert_eq!(
+            parse_board("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 -1").unwrap_err(),
+            FenError::BadMoveNumber {
+                name: "fullmove number",
+                field: "-1".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_overflowing_ranks() {
+        for rank in ["p8", "44p", "ppppppppp", "7pp"] {
+            let fen = format!("4k3/{rank}/8/8/8/8/8/4K3 w - - 0 1");
+            assert_eq!(
+                parse_board(&fen).unwrap_err(),
+                FenError::RankOverflow(7),
+                "{fen}"
+            );
+        }
+        assert!(parse_board("4k3/44/8/8/8/8/8/4K3 w - - 0 1").is_ok());
+        assert!(parse_board("4k3/1p1p1p1p/8/8/8/8/8/4K3 w - - 0 1").is_ok());
+    }
+
+    #[test]
+    fn each_side_needs_exactly_one_king() {
+        assert_eq!(
+            parse_board("4k3/8/8/8/8/8/8/3KK3 w - - 0 1").unwrap_err(),
+            FenError::KingCount {
+                color: "white",
+                count: 2
+            }
+        );
+        assert_eq!(
+            parse_board("8/8/8/8/8/8/8/4K3 w - - 0 1").unwrap_err(),
+            FenError::KingCount {
+                color: "black",
+                count: 0
+            }
+        );
+        assert!(parse_board("4k3/8/8/8/8/8/8/4K3 w - - 0 1").is_ok());
+    }
+
+    #[test]
+    fn parses_epd_operations() {
+        let (board, operations) = parse_epd(
+            r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";"#,
+        )
+        .unwrap();
+        assert_eq!(
+            board,
+            parse_board("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1").unwrap()
+        );
+        assert_eq!(operations.len(), 2);
+        assert_eq!(operations["bm"], "Qg6");
+        assert_eq!(operations["id"], "WAC.001");
+        // semicolons inside strings don't end the operation, operands stay as written
+        let (_, operations) =
+            parse_epd(r#"4k3/8/8/8/8/8/8/4K3 b - - c0 "a; b"; am Kd1 Kf1"#).unwrap();
+        assert_eq!(operations["c0"], "a; b");
+        assert_eq!(operations["am"], "Kd1 Kf1");
+        assert_eq!(
+            parse_epd("4k3/8/8/8/8/8/8/4K3 w -").unwrap_err(),
+            FenError::MissingField("en passant")
+        );
+        assert_eq!(
+            parse_epd("4k3/8/8/8/8/8/8/4K3 w - - id \"x").unwrap_err(),
+            FenError::UnterminatedString
+        );
+    }
+
+    #[test]
+    fn ranks_need_exactly_eight_squares() {
+        assert_eq!(
+            parse_board("rnbqkbn/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap_err(),
+            FenError::IncompleteRank { rank: 8, files: 7 }
+        );
+        assert_eq!(
+            parse_board("rnbqkbnrr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap_err(),
+            FenError::RankOverflow(8)
+        );
+        // a short rank isn't made up for by a long one after it
+        assert!(parse_board("rnbqkbn/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1").is_err());
+    }
+
+    #[test]
+    fn boards_parse_from_str() {
+        let board: Board = STARTING_FEN.parse().unwrap();
+        assert_eq!(board, parse_board(STARTING_FEN).unwrap());
+        assert_eq!(
+            "x".parse::<Board>().unwrap_err(),
+            FenError::MissingField("turn")
+        );
+    }
+
+    #[test]
+    fn positions_need_all_eight_ranks() {
+        assert_eq!(
+            parse_board("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap_err(),
+            FenError::MissingRanks(1)
+        );
+        // the last rank has to be complete as well
+        assert_eq!(
+            parse_board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKB w KQkq - 0 1").unwrap_err(),
+            FenError::IncompleteRank { rank: 1, files: 6 }
+        );
+        assert!(parse_board(STARTING_FEN).is_ok());
+    }
+
+    #[test]
+    fn lenient_parsing_fills_in_the_missing_fields() {
+        let bare = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+        let board = parse_board_lenient(bare).unwrap();
+        assert_eq!(board.turn(), Color::White);
+        assert_eq!(to_fen(&board), STARTING_FEN);
+        assert_eq!(
+            parse_board_lenient(STARTING_FEN).unwrap(),
+            parse_board(STARTING_FEN).unwrap()
+        );
+        assert!(parse_board(bare).is_err());
+        assert!(parse_board_lenient("rnbqkbnr/pppppppp/8").is_err());
+    }
+
+    #[test]
+    fn pawns_cant_stand_on_a_back_rank() {
+        let error = |fen: &str| parse_board(fen).unwrap_err();
+        assert_eq!(
+            error("P3k3/8/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::PawnOnBackRank("a8".to_string())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K2p b - - 0 1"),
+            FenError::PawnOnBackRank("h1".to_string())
+        );
+        assert!(parse_board("4k3/P7/8/8/8/8/7p/4K3 w - - 0 1").is_ok());
+    }
+
+    #[test]
+    fn turn_fields_parse_into_colors() {
+        assert_eq!(parse_turn_field("w"), Ok(Color::White));
+        assert_eq!(parse_turn_field("b"), Ok(Color::Black));
+        assert_eq!(
+            parse_turn_field("wb"),
+            Err(FenError::BadTurn("wb".to_string()))
+        );
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 b - - 0 1",
+        ] {
+            assert_eq!(to_fen(&parse_board(fen).unwrap()), fen);
+        }
+        assert_eq!(
+            parse_board("4k3/8/8/8/8/8/8/4K3 b").unwrap().turn(),
+            Color::Black
+        );
+    }
+
+    #[test]
+    fn the_position_field_follows_the_board_size() {
+        let files = Board::NUM_FILES as usize;
+        let ranks = Board::NUM_RANKS as usize;
+        let empty = vec![files.to_string(); ranks].join("/");
+        assert_eq!(
+            parse_position(&empty).unwrap(),
+            [Piece::NO_PIECE; Board::NUM_SQUARES]
+        );
+        let overflowing = vec![format!("{files}1"); ranks].join("/");
+        assert_eq!(
+            parse_position(&overflowing),
+            Err(FenError::RankOverflow(ranks))
+        );
+        let too_many = vec![files.to_string(); ranks + 1].join("/");
+        assert_eq!(parse_position(&too_many), Err(FenError::TooManyRanks));
+        for square in 0..Board::NUM_SQUARES {
+            let name = Board::square_name(square);
+            assert_eq!(Board::square_from_name(&name), Ok(square));
+        }
+        let board = Board::default().to_string();
+        assert_eq!(board.lines().count(), ranks + 1);
+        assert!(board.lines().all(|line| line.len() == 2 + 3 * files));
+    }
+
+    #[test]
+    fn parses_chess960_starts_with_the_king_off_the_e_file() {
+        let all = CastleType::fold_bits(CastleType::all());
+        for fen in [
+            "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w GEge - 0 1",
+            "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w KQkq - 0 1",
+        ] {
+            let board = parse_board_with(fen, CastleValidation::Strict).unwrap();
+            assert_eq!(board.castle_rights(), all, "{fen}");
+            assert_eq!(board.castle_king_file(Color::White), 5);
+            assert_eq!(board.castle_king_file(Color::Black), 5);
+            assert_eq!(
+                CastleType::all().map(|ty| board.castle_rook_file(ty)),
+                [4, 6, 4, 6]
+            );
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use crate::chess::{Board, GameResult, Undo};
+use crate::pgn;
+use crate::r#move::Move;
+
+/// a game played from some starting position, remembering every move on the way
+#[derive(Clone, Debug)]
+pub struct Game {
+    start: Board,
+    moves: Vec<Move>,
+    /// what it takes to take back each of `moves`
+    undos: Vec<Undo>,
+    board: Board,
+}
+
+impl Game {
+    pub fn new(start: Board) -> Self {
+        Self {
+            board: start.clone(),
+            start,
+            moves: Vec::new(),
+            undos: Vec::new(),
+        }
+    }
+
+    pub fn start(&self) -> &Board {
+        &self.start
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// the position after all the moves
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// plays `mv` if it's legal for the side to move
+    pub fn push(&mut self, mv: Move) -> Result<(), String> {
+        if !self.board.is_legal(mv) {
+            return Err(format!("illegal move '{}'", mv.to_uci()));
+        }
+        self.undos.push(self.board.make_move(mv));
+        self.moves.push(mv);
+        Ok(())
+    }
+
+    /// takes back the last move, `None` if there was none
+    pub fn pop(&mut self) -> Option<Move> {
+        let mv = self.moves.pop()?;
+        let undo = self.undos.pop()?;
+        self.board.unmake_move(mv, undo);
+        Some(mv)
+    }
+
+    /// the pgn of the game with its result, `*` while it's still going on
+    pub fn to_pgn(&self) -> String {
+        let result = match self.board.result() {
+            Some(GameResult::WhiteWins) => "1-0",
+            Some(GameResult::BlackWins) => "0-1",
+            Some(GameResult::Draw(_)) => "1/2-1/2",
+            None => "*",
+        };
+        let tags = HashMap::from([(String::from("Result"), String::from(result))]);
+        pgn::write_game(&self.start, &self.moves, &tags)
+    }
+
+    /// whether the game ended in a draw, the draw rules being applied as if claimed
+    pub fn is_draw(&self) -> bool {
+        matches!(self.board.result(), Some(GameResult::Draw(_)))
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new(Board::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push(game: &mut Game, moves: &[&str]) {
+        for uci in moves {
+            let mv = Move::from_uci(uci, game.board()).unwrap();
+            game.push(mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn push_and_pop_back_to_the_start() {
+        let mut game = Game::default();
+        push(&mut game, &["e2e4", "e7e5", "g1f3"]);
+        assert_eq!(game.moves().len(), 3);
+        assert!(game
+            .push(Move::from_uci("e1e3", game.board()).unwrap())
+            .is_err());
+        assert_eq!(game.moves().len(), 3);
+        assert!(
+            game.to_pgn().contains("1. e4 e5 2. Nf3 *"),
+            "{}",
+            game.to_pgn()
+        );
+        assert_eq!(game.pop().map(|mv| mv.to_uci()), Some("g1f3".to_string()));
+        while game.pop().is_some() {}
+        assert_eq!(game.board(), game.start());
+        assert_eq!(*game.board(), Board::default());
+        assert_eq!(game.pop(), None);
+    }
+
+    #[test]
+    fn repeating_the_position_draws() {
+        let mut game = Game::default();
+        push(
+            &mut game,
+            &["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1"],
+        );
+        assert!(!game.is_draw());
+        push(&mut game, &["f6g8"]);
+        assert!(game.is_draw());
+        assert!(game.to_pgn().contains("1/2-1/2"));
+    }
+}
@@ -0,0 +1,14 @@
+pub mod ai;
+pub mod baked_moves;
+pub mod binary;
+pub mod book;
+pub mod chess;
+pub mod fen;
+pub mod game;
+pub mod r#move;
+pub mod move_gen;
+pub mod perft;
+pub mod pgn;
+pub mod transposition;
+pub mod uci;
+pub mod zobrist;
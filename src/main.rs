@@ -1,9 +1,21 @@
-pub mod chess;
-pub mod fen;
-pub mod r#move;
+use std::io::{self, IsTerminal};
+
+use chessica::{chess, perft, uci};
 
 fn main() -> Result<(), String> {
-    let board = fen::parse_board(fen::STARTING_FEN)?;
-    println!("{board}");
+    match std::env::args().nth(1).as_deref() {
+        // `chessica perft` verifies the move generation against known node counts
+        Some("perft") => return perft::run_suite(),
+        Some("uci") => return run_uci(),
+        // guis start the engine without arguments and talk to it through a pipe
+        None if !io::stdin().is_terminal() => return run_uci(),
+        _ => {}
+    }
+    let board = chess::Board::default();
+    print!("{board}");
     Ok(())
 }
+
+fn run_uci() -> Result<(), String> {
+    uci::run(io::stdin().lock(), io::stdout().lock()).map_err(|e| e.to_string())
+}
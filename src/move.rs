@@ -1,4 +1,9 @@
-#[derive(Copy, Clone, Debug)]
+use std::fmt::{Display, Formatter};
+
+use crate::chess::{Board, Color, Piece};
+use crate::fen;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Move {
     EnPassant {
         from: usize,
@@ -12,9 +17,300 @@ pub enum Move {
     Castle {
         ty: CastleType,
     },
+    /// `piece` is the kind (Piece::QUEEN, ...) the pawn turns into
+    Promotion {
+        from: usize,
+        to: usize,
+        piece: u16,
+    },
 }
 
-#[derive(Copy, Clone, Debug)]
+impl Move {
+    pub fn new_ab(from: usize, to: usize) -> Self {
+        Self::AB { from, to }
+    }
+
+    pub fn new_en_passant(from: usize, to: usize, capture: usize) -> Self {
+        Self::EnPassant { from, to, capture }
+    }
+
+    pub fn new_castle(ty: CastleType) -> Self {
+        Self::Castle { ty }
+    }
+
+    pub fn new_promotion(from: usize, to: usize, piece: u16) -> Self {
+        debug_assert!(
+            [Piece::QUEEN, Piece::ROOK, Piece::BISHOP, Piece::KNIGHT].contains(&piece),
+            "can't promote to piece kind {piece}"
+        );
+        Self::Promotion { from, to, piece }
+    }
+
+    /// parses a move in UCI long algebraic notation (e2e4, e1g1, e7e8q).
+    /// the board is needed to tell castles and en passant captures from normal moves
+    pub fn from_uci(s: &str, board: &Board) -> Result<Self, String> {
+        if !s.is_ascii() || !(4..=5).contains(&s.len()) {
+            return Err(format!("illegal uci move '{s}'"));
+        }
+        let from = Board::square_from_name(&s[0..2])?;
+        let to = Board::square_from_name(&s[2..4])?;
+        let piece = board
+            .piece_at(from)
+            .ok_or_else(|| format!("no piece to move in uci move '{s}'"))?;
+        if let Some(c) = s[4..].chars().next() {
+            let kind = match c {
+                fen::FEN_BLACK_QUEEN => Piece::QUEEN,
+                fen::FEN_BLACK_ROOK => Piece::ROOK,
+                fen::FEN_BLACK_BISHOP => Piece::BISHOP,
+                fen::FEN_BLACK_KNIGHT => Piece::KNIGHT,
+                _ => return Err(format!("illegal promotion piece '{c}' in uci move '{s}'")),
+            };
+            if piece.kind() != Piece::PAWN {
+                return Err(format!("only pawns can promote, uci move '{s}'"));
+            }
+            return Ok(Self::new_promotion(from, to, kind));
+        }
+        if piece.kind() == Piece::KING {
+            let castle = Color::from_bits(piece.color()).and_then(|color| {
+                CastleType::from_king_move(from, to, color).or_else(|| {
+                    // Chess960 GUIs write castles as the king taking its own rook
+                    CastleType::all().into_iter().find(|&ty| {
+                        ty.color() == color
+                            && board.castle_king_square(ty) == from
+                            && board.castle_rook_square(ty) == to
+                            && board.castle_rights() & ty.get_bit() != 0
+                    })
+                })
+            });
+            if let Some(ty) = castle {
+                return Ok(Self::new_castle(ty));
+            }
+        }
+        if piece.kind() == Piece::PAWN
+            && board.en_passant_target() == Some(to)
+            && Board::file_of(from) != Board::file_of(to)
+        {
+            return Ok(Self::new_en_passant(
+                from,
+                to,
+                Board::square_index(Board::file_of(to), Board::rank_of(from)),
+            ));
+        }
+        Ok(Self::new_ab(from, to))
+    }
+
+    /// the move in standard algebraic notation (Nf3, exd5, O-O, e8=Q, Qxe7+, ...)
+    /// for the side to move on `board`, which the move has to be legal on
+    pub fn to_san(&self, board: &Board) -> String {
+        let mut san = match *self {
+            Self::Castle { ty } => match ty {
+                CastleType::WhiteShort | CastleType::BlackShort => "O-O".to_string(),
+                CastleType::WhiteLong | CastleType::BlackLong => "O-O-O".to_string(),
+            },
+            Self::AB { from, to }
+            | Self::Promotion { from, to, .. }
+            | Self::EnPassant { from, to, .. } => {
+                let piece = board[from];
+                let captures =
+                    matches!(self, Self::EnPassant { .. }) || board[to] != Piece::NO_PIECE;
+                let mut san = String::new();
+                if piece.kind() == Piece::PAWN {
+                    if captures {
+                        san.push((b'a' + Board::file_of(from) as u8) as char);
+                    }
+                } else {
+                    san.push(fen::fen_char(&Piece::new_unchecked(
+                        Piece::WHITE,
+                        piece.kind(),
+                    )));
+                    san.push_str(&disambiguation(from, to, board));
+                }
+                if captures {
+                    san.push('x');
+                }
+                san.push_str(&Board::square_name(to));
+                if let Self::Promotion { piece, .. } = self {
+                    san.push('=');
+                    san.push(fen::fen_char(&Piece::new_unchecked(Piece::WHITE, *piece)));
+                }
+                san
+            }
+        };
+        let after = board.with_move(*self);
+        let opponent = after.turn();
+        if after.is_check(opponent) {
+            san.push(if after.gen_legal(opponent).is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+
+    /// parses a move in standard algebraic notation for the side to move on `board`.
+    /// trailing check and annotation symbols (+, #, !, ?) are ignored
+    pub fn from_san(s: &str, board: &Board) -> Result<Self, String> {
+        let san = s.trim_end_matches(['+', '#', '!', '?']);
+        let legal = board.gen_legal(board.turn());
+        if san == "O-O" || san == "O-O-O" {
+            let short = san == "O-O";
+            return legal
+                .into_iter()
+                .find(|mv| match mv {
+                    Self::Castle { ty } => {
+                        short == matches!(ty, CastleType::WhiteShort | CastleType::BlackShort)
+                    }
+                    _ => false,
+                })
+                .ok_or_else(|| format!("castling '{s}' isn't legal"));
+        }
+
+        let (san, promotion) = match san.split_once('=') {
+            Some((san, piece)) => {
+                let kind = match piece {
+                    "Q" => Piece::QUEEN,
+                    "R" => Piece::ROOK,
+                    "B" => Piece::BISHOP,
+                    "N" => Piece::KNIGHT,
+                    _ => return Err(format!("illegal promotion piece in san move '{s}'")),
+                };
+                (san, Some(kind))
+            }
+            None => (san, None),
+        };
+        let mut chars = san.chars().peekable();
+        let kind = match chars.peek() {
+            Some(&fen::FEN_WHITE_KNIGHT) => Piece::KNIGHT,
+            Some(&fen::FEN_WHITE_BISHOP) => Piece::BISHOP,
+            Some(&fen::FEN_WHITE_ROOK) => Piece::ROOK,
+            Some(&fen::FEN_WHITE_QUEEN) => Piece::QUEEN,
+            Some(&fen::FEN_WHITE_KING) => Piece::KING,
+            _ => Piece::PAWN,
+        };
+        if kind != Piece::PAWN {
+            chars.next();
+        }
+        let rest: Vec<char> = chars.filter(|&c| c != 'x').collect();
+        if rest.len() < 2 {
+            return Err(format!("illegal san move '{s}'"));
+        }
+        let (disambiguation, target) = rest.split_at(rest.len() - 2);
+        let to = Board::square_from_name(&target.iter().collect::<String>())?;
+        let mut from_file = None;
+        let mut from_rank = None;
+        for &c in disambiguation {
+            match c {
+                'a'..='h' => from_file = Some(c as usize - 'a' as usize),
+                '1'..='8' => from_rank = Some(c as usize - '1' as usize),
+                _ => return Err(format!("unexpected character '{c}' in san move '{s}'")),
+            }
+        }
+
+        let mut candidates = legal.into_iter().filter(|mv| {
+            let (from, t, promotes_to) = match *mv {
+                Self::AB { from, to } | Self::EnPassant { from, to, .. } => (from, to, None),
+                Self::Promotion { from, to, piece } => (from, to, Some(piece)),
+                Self::Castle { .. } => return false,
+            };
+            t == to
+                && board[from].kind() == kind
+                && promotes_to == promotion
+                && from_file.is_none_or(|f| f == Board::file_of(from))
+                && from_rank.is_none_or(|r| r == Board::rank_of(from))
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some(mv), None) => Ok(mv),
+            (None, _) => Err(format!("san move '{s}' isn't legal")),
+            (Some(_), Some(_)) => Err(format!("san move '{s}' is ambiguous")),
+        }
+    }
+
+    /// whether `self` takes a piece of the other side on `board`. en passant always does,
+    /// castles never do
+    pub fn is_capture(&self, board: &Board) -> bool {
+        match *self {
+            Self::AB { from, to } | Self::Promotion { from, to, .. } => {
+                board[to] != Piece::NO_PIECE && board[to].color() != board[from].color()
+            }
+            Self::EnPassant { .. } => true,
+            Self::Castle { .. } => false,
+        }
+    }
+
+    /// the opposite of `is_capture`, so promotions without a capture are quiet too
+    pub fn is_quiet(&self, board: &Board) -> bool {
+        !self.is_capture(board)
+    }
+
+    /// the move in UCI long algebraic notation, castles are written as the king's move
+    pub fn to_uci(&self) -> String {
+        match *self {
+            Self::AB { from, to } | Self::EnPassant { from, to, .. } => {
+                format!("{}{}", Board::square_name(from), Board::square_name(to))
+            }
+            Self::Castle { ty } => format!(
+                "{}{}",
+                Board::square_name(ty.king_from()),
+                Board::square_name(ty.king_to())
+            ),
+            Self::Promotion { from, to, piece } => format!(
+                "{}{}{}",
+                Board::square_name(from),
+                Board::square_name(to),
+                fen::fen_char(&Piece::new_unchecked(Piece::BLACK, piece))
+            ),
+        }
+    }
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Self::Castle {
+                ty: CastleType::WhiteShort | CastleType::BlackShort,
+            } => write!(f, "O-O"),
+            Self::Castle {
+                ty: CastleType::WhiteLong | CastleType::BlackLong,
+            } => write!(f, "O-O-O"),
+            _ => write!(f, "{}", self.to_uci()),
+        }
+    }
+}
+
+/// the file, rank or square of `from` needed to tell the move apart from other
+/// moves of the same kind of piece to `to`; preferring the file over the rank
+fn disambiguation(from: usize, to: usize, board: &Board) -> String {
+    let kind = board[from].kind();
+    let others: Vec<usize> = board
+        .gen_legal(board.turn())
+        .into_iter()
+        .filter_map(|mv| match mv {
+            Move::AB { from: f, to: t } if t == to && f != from && board[f].kind() == kind => {
+                Some(f)
+            }
+            _ => None,
+        })
+        .collect();
+    let name = Board::square_name(from);
+    if others.is_empty() {
+        String::new()
+    } else if others
+        .iter()
+        .all(|&o| Board::file_of(o) != Board::file_of(from))
+    {
+        name[0..1].to_string()
+    } else if others
+        .iter()
+        .all(|&o| Board::rank_of(o) != Board::rank_of(from))
+    {
+        name[1..2].to_string()
+    } else {
+        name
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum CastleType {
     BlackLong,
     BlackShort,
@@ -27,6 +323,74 @@ impl CastleType {
     pub const BIT_BLACK_SHORT: u8 = 0b0010;
     pub const BIT_WHITE_LONG: u8 = 0b0100;
     pub const BIT_WHITE_SHORT: u8 = 0b1000;
+    /// every castle type, in declaration order
+    pub fn all() -> [Self; 4] {
+        [
+            Self::BlackLong,
+            Self::BlackShort,
+            Self::WhiteLong,
+            Self::WhiteShort,
+        ]
+    }
+
+    /// the castle rights of all `types` combined into one bitfield
+    pub fn fold_bits(types: impl IntoIterator<Item = Self>) -> u8 {
+        types.into_iter().fold(0, |bits, ty| bits | ty.get_bit())
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Self::BlackLong | Self::BlackShort => Color::Black,
+            Self::WhiteLong | Self::WhiteShort => Color::White,
+        }
+    }
+
+    /// the same castle for the other color
+    pub fn mirrored(self) -> Self {
+        match self {
+            Self::BlackLong => Self::WhiteLong,
+            Self::BlackShort => Self::WhiteShort,
+            Self::WhiteLong => Self::BlackLong,
+            Self::WhiteShort => Self::BlackShort,
+        }
+    }
+
+    /// the castle the king of `color` moving from `from` to `to` stands for, if any
+    pub fn from_king_move(from: usize, to: usize, color: Color) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|ty| ty.color() == color && ty.king_from() == from && ty.king_to() == to)
+    }
+
+    /// the king's square in standard chess, e1 for white and so on, which UCI writes
+    /// castles from. where the king castles from on a board, in Chess960 as well, is
+    /// `Board::castle_king_square`
+    pub fn king_from(self) -> usize {
+        match self {
+            Self::BlackLong | Self::BlackShort => 4,
+            Self::WhiteLong | Self::WhiteShort => 60,
+        }
+    }
+
+    pub fn king_to(self) -> usize {
+        match self {
+            Self::BlackLong => 2,
+            Self::BlackShort => 6,
+            Self::WhiteLong => 58,
+            Self::WhiteShort => 62,
+        }
+    }
+
+    /// also the square the king passes on its way
+    pub fn rook_to(self) -> usize {
+        match self {
+            Self::BlackLong => 3,
+            Self::BlackShort => 5,
+            Self::WhiteLong => 59,
+            Self::WhiteShort => 61,
+        }
+    }
+
     pub fn get_bit(self) -> u8 {
         match self {
             Self::BlackLong => Self::BIT_BLACK_LONG,
@@ -36,3 +400,278 @@ impl CastleType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san(fen: &str, uci: &str) -> String {
+        let board = fen::parse_board(fen).unwrap();
+        Move::from_uci(uci, &board).unwrap().to_san(&board)
+    }
+
+    #[test]
+    fn to_san_formats_moves() {
+        let start = fen::STARTING_FEN;
+        assert_eq!(san(start, "g1f3"), "Nf3");
+        assert_eq!(san(start, "e2e4"), "e4");
+        let exchange = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
+        assert_eq!(san(exchange, "e4d5"), "exd5");
+        let en_passant = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+        assert_eq!(san(en_passant, "e5f6"), "exf6");
+        let castles = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san(castles, "e1g1"), "O-O");
+        assert_eq!(san(castles, "e1c1"), "O-O-O");
+        let promotion = "8/4P3/8/8/k7/8/8/7K w - - 0 1";
+        assert_eq!(san(promotion, "e7e8q"), "e8=Q+");
+        assert_eq!(san(promotion, "e7e8n"), "e8=N");
+    }
+
+    #[test]
+    fn to_san_adds_check_and_mate() {
+        let check = "4k3/4r3/8/8/8/8/8/4QK2 w - - 0 1";
+        assert_eq!(san(check, "e1e7"), "Qxe7+");
+        let scholars = "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+        assert_eq!(san(scholars, "h5f7"), "Qxf7#");
+    }
+
+    #[test]
+    fn to_san_disambiguates() {
+        // by file, by rank, and by both when neither alone is enough
+        assert_eq!(san("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "b1d2"), "Nbd2");
+        assert_eq!(san("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "f1d2"), "Nfd2");
+        assert_eq!(san("4k3/8/8/8/R7/8/8/R3K3 w - - 0 1", "a1a2"), "R1a2");
+        assert_eq!(san("4k3/8/8/8/R7/8/8/R3K3 w - - 0 1", "a4a2"), "R4a2");
+        let queens = "4k3/8/8/8/Q1Q5/8/Q7/4K3 w - - 0 1";
+        assert_eq!(san(queens, "a4b3"), "Qa4b3");
+        // a pinned knight doesn't make the other one ambiguous
+        assert_eq!(san("4k3/4r3/8/8/8/8/4N3/1N2K3 w - - 0 1", "b1c3"), "Nc3");
+    }
+
+    #[test]
+    fn from_san_resolves_moves() {
+        let knights = fen::parse_board("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1").unwrap();
+        assert!(Move::from_san("Nd2", &knights).is_err());
+        let nbd2 = Move::from_san("Nbd2", &knights).unwrap();
+        assert_eq!(nbd2.to_uci(), "b1d2");
+        assert_eq!(Move::from_san("Nfd2!?", &knights).unwrap().to_uci(), "f1d2");
+        assert!(Move::from_san("Nd4", &knights).is_err());
+        assert!(Move::from_san("N", &knights).is_err());
+        let promotion = fen::parse_board("8/4P3/8/8/k7/8/8/7K w - - 0 1").unwrap();
+        assert_eq!(
+            Move::from_san("e8=Q+", &promotion).unwrap(),
+            Move::new_promotion(12, 4, Piece::QUEEN)
+        );
+        assert!(Move::from_san("e8", &promotion).is_err());
+        assert!(Move::from_san("e8=K", &promotion).is_err());
+    }
+
+    #[test]
+    fn from_san_round_trips_to_san() {
+        let board = fen::parse_board(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        for mv in board.gen_legal(board.turn()) {
+            assert_eq!(Move::from_san(&mv.to_san(&board), &board), Ok(mv));
+        }
+    }
+
+    #[test]
+    fn from_uci_parses_every_kind_of_move() {
+        let board = Board::default();
+        assert_eq!(Move::from_uci("e2e4", &board), Ok(Move::new_ab(52, 36)));
+        for bad in ["e2e9", "zz", "e2e4qq", "e3e4", "e2e4k"] {
+            assert!(Move::from_uci(bad, &board).is_err(), "{bad}");
+        }
+        let board = fen::parse_board("4k3/1P6/8/3pP3/8/8/8/R3K2R w KQ d6 0 1").unwrap();
+        assert_eq!(
+            Move::from_uci("b7b8q", &board),
+            Ok(Move::new_promotion(9, 1, Piece::QUEEN))
+        );
+        assert_eq!(
+            Move::from_uci("b7b8n", &board),
+            Ok(Move::new_promotion(9, 1, Piece::KNIGHT))
+        );
+        assert_eq!(
+            Move::from_uci("e1g1", &board),
+            Ok(Move::new_castle(CastleType::WhiteShort))
+        );
+        assert_eq!(
+            Move::from_uci("e5d6", &board),
+            Ok(Move::new_en_passant(28, 19, 27))
+        );
+        assert_eq!(Move::from_uci("e1f1", &board), Ok(Move::new_ab(60, 61)));
+    }
+
+    #[test]
+    fn to_uci_round_trips() {
+        assert_eq!(Move::new_ab(52, 36).to_uci(), "e2e4");
+        assert_eq!(Move::new_castle(CastleType::BlackLong).to_uci(), "e8c8");
+        assert_eq!(Move::new_en_passant(28, 19, 27).to_uci(), "e5d6");
+        assert_eq!(Move::new_promotion(12, 4, Piece::ROOK).to_uci(), "e7e8r");
+        for fen in [
+            "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ] {
+            let board = fen::parse_board(fen).unwrap();
+            for mv in board.gen_legal(board.turn()) {
+                assert_eq!(Move::from_uci(&mv.to_uci(), &board), Ok(mv));
+            }
+        }
+    }
+
+    #[test]
+    fn display_writes_castles_like_san() {
+        assert_eq!(Move::new_ab(52, 36).to_string(), "e2e4");
+        assert_eq!(Move::new_castle(CastleType::WhiteShort).to_string(), "O-O");
+        assert_eq!(Move::new_castle(CastleType::BlackLong).to_string(), "O-O-O");
+        assert_eq!(
+            Move::new_promotion(12, 4, Piece::KNIGHT).to_string(),
+            "e7e8n"
+        );
+    }
+
+    #[test]
+    fn constructors_build_their_variant() {
+        assert_eq!(Move::new_ab(52, 36), Move::AB { from: 52, to: 36 });
+        assert_eq!(
+            Move::new_en_passant(28, 19, 27),
+            Move::EnPassant {
+                from: 28,
+                to: 19,
+                capture: 27
+            }
+        );
+        assert_eq!(
+            Move::new_castle(CastleType::BlackLong),
+            Move::Castle {
+                ty: CastleType::BlackLong
+            }
+        );
+    }
+
+    #[test]
+    fn new_promotion_builds_every_piece() {
+        for kind in [Piece::QUEEN, Piece::ROOK, Piece::BISHOP, Piece::KNIGHT] {
+            match Move::new_promotion(12, 4, kind) {
+                Move::Promotion { from, to, piece } => {
+                    assert_eq!((from, to, piece), (12, 4, kind))
+                }
+                mv => panic!("expected a promotion, got {mv:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn moves_compare_and_hash_by_value() {
+        use std::collections::HashSet;
+        let moves: HashSet<Move> = [
+            Move::new_ab(52, 36),
+            Move::new_ab(52, 36),
+            Move::new_ab(52, 44),
+            Move::new_en_passant(28, 19, 27),
+            Move::new_promotion(12, 4, Piece::QUEEN),
+            Move::new_promotion(12, 4, Piece::KNIGHT),
+            Move::new_castle(CastleType::WhiteShort),
+            Move::new_castle(CastleType::WhiteShort),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(moves.len(), 6);
+        assert_ne!(Move::new_ab(28, 19), Move::new_en_passant(28, 19, 27));
+    }
+
+    fn square(name: &str) -> usize {
+        Board::square_from_name(name).unwrap()
+    }
+
+    #[test]
+    fn castle_type_from_king_moves() {
+        let castle = |from, to, color| CastleType::from_king_move(square(from), square(to), color);
+        assert_eq!(
+            castle("e1", "g1", Color::White),
+            Some(CastleType::WhiteShort)
+        );
+        assert_eq!(
+            castle("e1", "c1", Color::White),
+            Some(CastleType::WhiteLong)
+        );
+        assert_eq!(
+            castle("e8", "c8", Color::Black),
+            Some(CastleType::BlackLong)
+        );
+        assert_eq!(
+            castle("e8", "g8", Color::Black),
+            Some(CastleType::BlackShort)
+        );
+        assert_eq!(castle("e8", "c8", Color::White), None);
+        assert_eq!(castle("e1", "f1", Color::White), None);
+    }
+
+    #[test]
+    fn all_castle_types() {
+        let all = CastleType::all();
+        assert_eq!(CastleType::fold_bits(all), 0b1111);
+        assert_eq!(
+            all.map(CastleType::get_bit),
+            [0b0001, 0b0010, 0b0100, 0b1000]
+        );
+        for ty in all {
+            assert_eq!(ty.mirrored().mirrored(), ty);
+            assert_ne!(ty.mirrored().color(), ty.color());
+        }
+    }
+
+    #[test]
+    fn castle_squares() {
+        let board = Board::default();
+        let squares = |ty: CastleType| {
+            (
+                ty.king_from(),
+                ty.king_to(),
+                board.castle_rook_square(ty),
+                ty.rook_to(),
+            )
+        };
+        assert_eq!(
+            squares(CastleType::WhiteShort),
+            (square("e1"), square("g1"), square("h1"), square("f1"))
+        );
+        assert_eq!(
+            squares(CastleType::BlackLong),
+            (square("e8"), square("c8"), square("a8"), square("d8"))
+        );
+    }
+
+    #[test]
+    fn to_san_disambiguates_by_the_least_needed() {
+        // three queens reach e1: the file tells e4 apart, the rank h1 and only the
+        // whole square h4, which shares its file with h1 and its rank with e4
+        let queens = "1k6/8/8/8/4Q2Q/8/8/K6Q w - - 0 1";
+        assert_eq!(san(queens, "e4e1"), "Qee1");
+        assert_eq!(san(queens, "h1e1"), "Q1e1");
+        assert_eq!(san(queens, "h4e1"), "Qh4e1");
+        // squares only one of them reaches need nothing
+        assert_eq!(san(queens, "e4e8"), "Qe8+");
+        for uci in ["e4e1", "h1e1", "h4e1"] {
+            let board = fen::parse_board(queens).unwrap();
+            let mv = Move::from_uci(uci, &board).unwrap();
+            assert_eq!(Move::from_san(&mv.to_san(&board), &board), Ok(mv));
+        }
+    }
+
+    #[test]
+    fn captures_and_quiet_moves() {
+        let board = fen::parse_board("r3k3/1P6/8/3pP3/8/8/8/4K2R w Kq d6 0 1").unwrap();
+        let mv = |uci| Move::from_uci(uci, &board).unwrap();
+        assert!(mv("e5d6").is_capture(&board));
+        assert!(mv("b7a8q").is_capture(&board));
+        assert!(!mv("b7a8q").is_quiet(&board));
+        assert!(!mv("e5e6").is_capture(&board));
+        assert!(!mv("b7b8q").is_capture(&board));
+        assert!(mv("b7b8q").is_quiet(&board));
+        assert!(mv("e1g1").is_quiet(&board));
+        assert!(mv("h1h8").is_quiet(&board));
+    }
+}
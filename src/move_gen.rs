@@ -0,0 +1,1691 @@
+use std::ops::{AddAssign, Range};
+
+use crate::baked_moves::{BETWEEN, KING_MOVES, KNIGHT_MOVES, PAWN_ATTACKS, RAYS};
+use crate::chess::{self, Board, Color, DrawReason, GameResult, GameStatus, Piece, PieceKind};
+use crate::r#move::{CastleType, Move};
+
+/// (file delta, rank delta) pairs. the first four are the rook's directions, the
+/// last four the bishop's
+pub const QUEEN_DIRECTIONS: [(i32, i32); 8] = [
+    (0, 1),
+    (0, -1),
+    (1, 0),
+    (-1, 0),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+pub const KING_OFFSETS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+pub const KNIGHT_OFFSETS: [(i32, i32); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+
+/// the leaves of `Board::perft_detailed`, all of them and those reached by each kind of move
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PerftStats {
+    pub nodes: u64,
+    pub captures: u64,
+    pub en_passant: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    pub checkmates: u64,
+}
+
+impl AddAssign for PerftStats {
+    fn add_assign(&mut self, other: Self) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passant += other.en_passant;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.checkmates += other.checkmates;
+    }
+}
+
+/// which moves `Board::gen_legal_opts` generates. the default is all of them
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GenOpts {
+    /// promotions to rooks, bishops and knights. without them, a pawn reaching the last
+    /// rank only promotes to a queen, which is almost always best anyway
+    pub underpromotions: bool,
+}
+
+impl Default for GenOpts {
+    fn default() -> Self {
+        Self {
+            underpromotions: true,
+        }
+    }
+}
+
+impl GenOpts {
+    fn allows(self, mv: Move) -> bool {
+        match mv {
+            Move::Promotion { piece, .. } => self.underpromotions || piece == Piece::QUEEN,
+            _ => true,
+        }
+    }
+}
+
+impl Board {
+    /// the moves of `color`'s pieces, ignoring whether they leave the king in check.
+    /// the order is fixed: by the square moved from, a8 to h1, and for each piece in the
+    /// order of its directions (`QUEEN_DIRECTIONS`, `KNIGHT_OFFSETS`, ...), nearest square
+    /// first. pawns push before they capture and promote to a queen, rook, bishop and
+    /// knight in that order; castles come after the king's steps, as in `CastleType::all`
+    pub fn gen_pseudo_legal(&self, color: Color) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for (pos, p) in self.pieces_of(color) {
+            gen_pseudo_legal_for_piece(pos, p, self, &mut moves);
+        }
+        moves
+    }
+
+    /// the pseudo-legal moves of the piece on `pos`, none for an empty square
+    pub fn moves_from(&self, pos: usize) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let p = self[pos];
+        if p != Piece::NO_PIECE {
+            gen_pseudo_legal_for_piece(pos, p, self, &mut moves);
+        }
+        moves
+    }
+
+    /// the moves of the piece on `pos` that don't leave its own king in check,
+    /// regardless of whose turn it is. none for an empty square
+    pub fn legal_moves_from(&self, pos: usize) -> Vec<Move> {
+        let Some(color) = Color::from_bits(self[pos].color()) else {
+            return Vec::new();
+        };
+        self.moves_from(pos)
+            .into_iter()
+            .filter(|&mv| self.leaves_king_safe(mv, color))
+            .collect()
+    }
+
+    /// the pseudo-legal moves of `color` that don't leave its own king in check.
+    /// the king's attackers are looked up with the occupancy each move leaves behind, so
+    /// this also catches en passant captures that expose the king along the rank both
+    /// pawns leave (8/8/8/8/k2Pp2Q/8/8/4K3 b - d3).
+    /// castling additionally mustn't start from or pass through an attacked square.
+    /// the moves come in the order of `gen_pseudo_legal`, which callers may rely on
+    pub fn gen_legal(&self, color: Color) -> Vec<Move> {
+        self.gen_legal_opts(color, GenOpts::default())
+    }
+
+    /// `gen_legal`, leaving out the moves `opts` switches off
+    pub fn gen_legal_opts(&self, color: Color, opts: GenOpts) -> Vec<Move> {
+        self.legal_candidates(color)
+            .into_iter()
+            .filter(|&mv| opts.allows(mv))
+            .filter(|&mv| self.leaves_king_safe(mv, color))
+            .collect()
+    }
+
+    /// the legal moves of the side to move in standard algebraic notation, check and mate
+    /// suffixes included, ordered by the moving piece (pawns first) and then its target square
+    pub fn legal_moves_san(&self) -> Vec<String> {
+        let mut moves: Vec<(u16, usize, String)> = self
+            .gen_legal(self.turn())
+            .into_iter()
+            .map(|mv| {
+                let (from, to) = match mv {
+                    Move::AB { from, to }
+                    | Move::EnPassant { from, to, .. }
+                    | Move::Promotion { from, to, .. } => (from, to),
+                    Move::Castle { ty } => (self.castle_king_square(ty), ty.king_to()),
+                };
+                (self[from].kind(), to, mv.to_san(self))
+            })
+            .collect();
+        moves.sort();
+        moves.into_iter().map(|(_, _, san)| san).collect()
+    }
+
+    /// the number of moves `gen_legal` would return, without collecting them.
+    /// only one piece's moves are held at a time and none of them is played on the board
+    pub fn count_legal_moves(&self, color: Color) -> usize {
+        let mut moves = Vec::new();
+        let mut count = 0;
+        for (pos, p) in self.pieces_of(color) {
+            moves.clear();
+            gen_pseudo_legal_for_piece(pos, p, self, &mut moves);
+            count += moves
+                .iter()
+                .filter(|&&mv| self.leaves_king_safe(mv, color))
+                .count();
+        }
+        count
+    }
+
+    /// the pseudo-legal moves of `color` that may be legal. in check, that's only king
+    /// moves and, against a single checker, the moves taking it or stepping in its way
+    fn legal_candidates(&self, color: Color) -> Vec<Move> {
+        let Some(king) = self.king_square(color) else {
+            return self.gen_pseudo_legal(color);
+        };
+        let occupied = self.occupancy(Color::White) | self.occupancy(Color::Black);
+        let checkers = self.attackers_bitboard(king, color.opposite(), occupied);
+        if checkers == 0 {
+            return self.gen_pseudo_legal(color);
+        }
+        if checkers.count_ones() > 1 {
+            return self.moves_from(king);
+        }
+        let checker = checkers.trailing_zeros() as usize;
+        let targets = checkers | BETWEEN[king][checker];
+        let mut moves = self.gen_pseudo_legal(color);
+        moves.retain(|&mv| match mv {
+            Move::AB { from, to } | Move::Promotion { from, to, .. } => {
+                from == king || targets & (1 << to) != 0
+            }
+            Move::EnPassant { to, capture, .. } => capture == checker || targets & (1 << to) != 0,
+            Move::Castle { .. } => false,
+        });
+        moves
+    }
+
+    /// checks that `mv` is well-formed for the side to move, e.g. after reading it from
+    /// somewhere else: its squares are on the board, it starts from one of the mover's
+    /// pieces and it fits that piece. says nothing about whether the move is legal
+    pub fn validate_move(&self, mv: Move) -> Result<(), String> {
+        let turn = self.turn();
+        let (from, to) = match mv {
+            Move::AB { from, to }
+            | Move::EnPassant { from, to, .. }
+            | Move::Promotion { from, to, .. } => (from, to),
+            Move::Castle { ty } => {
+                if ty.color() != turn {
+                    return Err(format!("{ty:?} castling isn't {turn:?}'s"));
+                }
+                if self.castle_rights() & ty.get_bit() == 0 {
+                    return Err(format!("no right to {ty:?} castling left"));
+                }
+                (self.castle_king_square(ty), ty.king_to())
+            }
+        };
+        for square in [from, to] {
+            if square >= Board::NUM_SQUARES {
+                return Err(format!("square index {square} is off the board"));
+            }
+        }
+        let piece = self[from];
+        if piece == Piece::NO_PIECE {
+            return Err(format!("no piece on {}", Board::square_name(from)));
+        }
+        if piece.color() != turn.bits() {
+            return Err(format!(
+                "the piece on {} isn't {turn:?}'s",
+                Board::square_name(from)
+            ));
+        }
+        // a chess960 king may already stand where castling takes it
+        if !matches!(mv, Move::Castle { .. }) {
+            if from == to {
+                return Err(format!(
+                    "the move starts and ends on {}",
+                    Board::square_name(from)
+                ));
+            }
+            if self[to].color() == turn.bits() {
+                return Err(format!(
+                    "{} is taken by one of {turn:?}'s own pieces",
+                    Board::square_name(to)
+                ));
+            }
+        }
+        match mv {
+            Move::EnPassant { capture, .. } => {
+                if piece.kind() != Piece::PAWN {
+                    return Err("only pawns capture en passant".to_string());
+                }
+                if self.en_passant_target() != Some(to) {
+                    return Err(format!(
+                        "{} isn't the en passant target",
+                        Board::square_name(to)
+                    ));
+                }
+                if capture >= Board::NUM_SQUARES
+                    || self[capture] != Piece::new_unchecked(turn.opposite().bits(), Piece::PAWN)
+                {
+                    return Err("the en passant capture doesn't take a pawn".to_string());
+                }
+            }
+            Move::Promotion {
+                piece: promoted, ..
+            } => {
+                if piece.kind() != Piece::PAWN {
+                    return Err("only pawns promote".to_string());
+                }
+                if ![Piece::QUEEN, Piece::ROOK, Piece::BISHOP, Piece::KNIGHT].contains(&promoted) {
+                    return Err("pawns only promote to queen, rook, bishop or knight".to_string());
+                }
+                let last_rank = match turn {
+                    Color::White => Board::NUM_RANKS as usize - 1,
+                    Color::Black => 0,
+                };
+                if Board::rank_of(to) != last_rank {
+                    return Err(format!("{} isn't on the last rank", Board::square_name(to)));
+                }
+            }
+            Move::AB { .. } | Move::Castle { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// whether `mv` is one of the moves `gen_legal` returns for the side to move.
+    /// only the moves of the piece `mv` starts from are generated to find out
+    pub fn is_legal(&self, mv: Move) -> bool {
+        let from = match mv {
+            Move::AB { from, .. } | Move::EnPassant { from, .. } | Move::Promotion { from, .. } => {
+                from
+            }
+            Move::Castle { ty } => self.castle_king_square(ty),
+        };
+        self.validate_move(mv).is_ok()
+            && self.moves_from(from).contains(&mv)
+            && self.leaves_king_safe(mv, self.turn())
+    }
+
+    // whether the pseudo-legal `mv` leaves `color`'s king out of check, without playing it:
+    // the king's attackers are looked up with the occupancy the move leaves behind, minus
+    // the piece it takes
+    fn leaves_king_safe(&self, mv: Move, color: Color) -> bool {
+        let Some(king) = self.king_square(color) else {
+            return true;
+        };
+        let enemy = color.opposite();
+        let occupied = self.occupancy(Color::White) | self.occupancy(Color::Black);
+        let (vacated, landed, taken, king) = match mv {
+            Move::AB { from, to } | Move::Promotion { from, to, .. } => {
+                let king = if from == king { to } else { king };
+                (1 << from, 1 << to, 1 << to, king)
+            }
+            Move::EnPassant { from, to, capture } => {
+                (1 << from | 1 << capture, 1 << to, 1 << capture, king)
+            }
+            Move::Castle { ty } => {
+                // the king mustn't castle out of or through check, wherever it starts
+                let king_from = self.castle_king_square(ty);
+                let crossed = BETWEEN[king_from][ty.king_to()] | 1 << king_from;
+                if chess::squares(crossed).any(|sq| self.is_square_attacked(sq, enemy)) {
+                    return false;
+                }
+                let rook_from = self.castle_rook_square(ty);
+                (
+                    1 << king_from | 1 << rook_from,
+                    1 << ty.king_to() | 1 << ty.rook_to(),
+                    0,
+                    ty.king_to(),
+                )
+            }
+        };
+        let occupied = occupied & !vacated | landed;
+        self.attackers_bitboard(king, enemy, occupied) & !taken == 0
+    }
+
+    /// only the legal moves of `color` that capture something, including en passant
+    /// and capturing promotions. quiet moves aren't generated in the first place
+    pub fn gen_captures(&self, color: Color) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for (pos, p) in self.pieces_of(color) {
+            gen_captures_for_piece(pos, p, self, &mut moves);
+        }
+        moves.retain(|&mv| self.leaves_king_safe(mv, color));
+        moves
+    }
+
+    /// whether any piece of `by_color` attacks `square`, regardless of what stands on it
+    pub fn is_square_attacked(&self, square: usize, by_color: Color) -> bool {
+        self.attackers_bitboard(
+            square,
+            by_color,
+            self.occupancy(Color::White) | self.occupancy(Color::Black),
+        ) != 0
+    }
+
+    /// the squares of all pieces of `by_color` that attack `square`, from a8 to h1
+    pub fn attackers_to(&self, square: usize, by_color: Color) -> Vec<usize> {
+        let occupied = self.occupancy(Color::White) | self.occupancy(Color::Black);
+        chess::squares(self.attackers_bitboard(square, by_color, occupied)).collect()
+    }
+
+    /// the pieces of `by_color` attacking `square` if only the pieces on `occupied` were left,
+    /// so taking pieces out of `occupied` reveals the sliders behind them
+    pub fn attackers_bitboard(&self, square: usize, by_color: Color, occupied: u64) -> u64 {
+        let bitboard = |kind| self.bitboard(by_color, kind);
+        // a pawn attacking `square` stands where a pawn of the other color on `square` attacks
+        let mut attackers = PAWN_ATTACKS[by_color.opposite() as usize][square]
+            & bitboard(PieceKind::Pawn)
+            | KNIGHT_MOVES[square] & bitboard(PieceKind::Knight)
+            | KING_MOVES[square] & bitboard(PieceKind::King);
+        let queens = bitboard(PieceKind::Queen);
+        let rooks = bitboard(PieceKind::Rook) | queens;
+        let bishops = bitboard(PieceKind::Bishop) | queens;
+        for (direction, &(file_delta, rank_delta)) in QUEEN_DIRECTIONS.iter().enumerate() {
+            let sliders = if file_delta == 0 || rank_delta == 0 {
+                rooks
+            } else {
+                bishops
+            };
+            if let Some(nearest) = nearest_blocker(square, direction, occupied) {
+                attackers |= sliders & (1 << nearest);
+            }
+        }
+        attackers & occupied
+    }
+
+    /// every square a piece of `color` attacks, bit n standing for index n. squares
+    /// behind the first piece in a slider's way aren't attacked, whoever that piece is
+    pub fn attacked_squares(&self, color: Color) -> u64 {
+        let occupied = self.occupancy(Color::White) | self.occupancy(Color::Black);
+        let mut attacked = 0;
+        for kind in PieceKind::ALL {
+            for square in chess::squares(self.bitboard(color, kind)) {
+                attacked |= match kind {
+                    PieceKind::Pawn => PAWN_ATTACKS[color as usize][square],
+                    PieceKind::Knight => KNIGHT_MOVES[square],
+                    PieceKind::King => KING_MOVES[square],
+                    PieceKind::Rook => slider_attacks(square, 0..4, occupied),
+                    PieceKind::Bishop => slider_attacks(square, 4..8, occupied),
+                    PieceKind::Queen => slider_attacks(square, 0..8, occupied),
+                };
+            }
+        }
+        attacked
+    }
+
+    /// the squares of the pieces giving check to `color`'s king, from a8 to h1.
+    /// two of them are a double check, which only a king move gets out of
+    pub fn checkers(&self, color: Color) -> Vec<usize> {
+        self.king_square(color)
+            .map(|king| self.attackers_to(king, color.opposite()))
+            .unwrap_or_default()
+    }
+
+    /// whether `color`'s king is attacked. returns false rather than panicking for a
+    /// side without a king, which only a board still being set up with `set_piece` can
+    /// have, so such a board reads as not in check
+    pub fn is_check(&self, color: Color) -> bool {
+        self.king_square(color)
+            .is_some_and(|king| self.is_square_attacked(king, color.opposite()))
+    }
+
+    /// false if the side that just moved left its king in check, which no legal game
+    /// can end up in. fens can still describe such positions
+    pub fn is_legal_position(&self) -> bool {
+        !self.is_check(self.turn().opposite())
+    }
+
+    /// whether playing `mv` for the side to move puts the other king in check
+    pub fn gives_check(&self, mv: Move) -> bool {
+        self.with_move(mv).is_check(self.turn().opposite())
+    }
+
+    pub fn is_checkmate(&self, color: Color) -> bool {
+        self.is_check(color) && self.gen_legal(color).is_empty()
+    }
+
+    pub fn is_stalemate(&self, color: Color) -> bool {
+        !self.is_check(color) && self.gen_legal(color).is_empty()
+    }
+
+    /// the number of leaf nodes `depth` legal plies away from this position
+    pub fn perft(&self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.gen_legal(self.turn());
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut board = self.clone();
+        moves
+            .into_iter()
+            .map(|mv| {
+                let undo = board.make_move(mv);
+                let nodes = board.perft(depth - 1);
+                board.unmake_move(mv, undo);
+                nodes
+            })
+            .sum()
+    }
+
+    /// `perft` split up by the root moves, for finding the culprit of a wrong count
+    pub fn perft_divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        let mut board = self.clone();
+        self.gen_legal(self.turn())
+            .into_iter()
+            .map(|mv| {
+                let undo = board.make_move(mv);
+                let nodes = board.perft(depth.saturating_sub(1));
+                board.unmake_move(mv, undo);
+                (mv, nodes)
+            })
+            .collect()
+    }
+
+    /// `perft` with the leaves counted by the move leading to them, like the published
+    /// tables do. a leaf can count as several kinds at once, a capturing promotion say
+    pub fn perft_detailed(&self, depth: u32) -> PerftStats {
+        let mut stats = PerftStats::default();
+        if depth == 0 {
+            stats.nodes = 1;
+            return stats;
+        }
+        let mut board = self.clone();
+        for mv in self.gen_legal(self.turn()) {
+            if depth > 1 {
+                let undo = board.make_move(mv);
+                stats += board.perft_detailed(depth - 1);
+                board.unmake_move(mv, undo);
+                continue;
+            }
+            stats.nodes += 1;
+            stats.captures += u64::from(mv.is_capture(&board));
+            stats.en_passant += u64::from(matches!(mv, Move::EnPassant { .. }));
+            stats.castles += u64::from(matches!(mv, Move::Castle { .. }));
+            stats.promotions += u64::from(matches!(mv, Move::Promotion { .. }));
+            let undo = board.make_move(mv);
+            let color = board.turn();
+            if board.is_check(color) {
+                stats.checks += 1;
+                stats.checkmates += u64::from(board.gen_legal(color).is_empty());
+            }
+            board.unmake_move(mv, undo);
+        }
+        stats
+    }
+
+    /// whether the side to move is checkmated or stalemated
+    pub fn status(&self) -> GameStatus {
+        let color = self.turn();
+        if !self.gen_legal(color).is_empty() {
+            GameStatus::Ongoing
+        } else if self.is_check(color) {
+            GameStatus::Checkmate {
+                winner: color.opposite(),
+            }
+        } else {
+            GameStatus::Stalemate
+        }
+    }
+
+    /// the result if the game is over in this position, mate taking precedence over the
+    /// draw rules and those being applied right away as if claimed
+    pub fn result(&self) -> Option<GameResult> {
+        match self.status() {
+            GameStatus::Checkmate {
+                winner: Color::White,
+            } => Some(GameResult::WhiteWins),
+            GameStatus::Checkmate {
+                winner: Color::Black,
+            } => Some(GameResult::BlackWins),
+            GameStatus::Stalemate => Some(GameResult::Draw(DrawReason::Stalemate)),
+            GameStatus::Ongoing if self.is_insufficient_material() => {
+                Some(GameResult::Draw(DrawReason::InsufficientMaterial))
+            }
+            GameStatus::Ongoing if self.is_threefold_repetition() => {
+                Some(GameResult::Draw(DrawReason::Threefold))
+            }
+            GameStatus::Ongoing if self.is_fifty_move_draw() => {
+                Some(GameResult::Draw(DrawReason::FiftyMoves))
+            }
+            GameStatus::Ongoing => None,
+        }
+    }
+}
+
+/// the square `(file delta, rank delta)` away from `pos`, if it's still on the board
+pub const fn offset(pos: usize, (file_delta, rank_delta): (i32, i32)) -> Option<usize> {
+    let file = Board::file_of(pos) as i32 + file_delta;
+    let rank = Board::rank_of(pos) as i32 + rank_delta;
+    if file >= 0 && file < Board::NUM_FILES as i32 && rank >= 0 && rank < Board::NUM_RANKS as i32 {
+        Some(Board::square_index(file as usize, rank as usize))
+    } else {
+        None
+    }
+}
+
+/// the square of the first piece on `occupied` in `QUEEN_DIRECTIONS[direction]` from
+/// `square`, `None` if the way is free up to the edge of the board
+fn nearest_blocker(square: usize, direction: usize, occupied: u64) -> Option<usize> {
+    let blockers = RAYS[square][direction] & occupied;
+    (blockers != 0).then(|| nearest_square(blockers, direction))
+}
+
+/// the square of `squares`, all on one ray in `QUEEN_DIRECTIONS[direction]`, that's
+/// nearest to the start of the ray
+fn nearest_square(squares: u64, direction: usize) -> usize {
+    // the lowest bit on rays towards h1, the highest otherwise
+    let (file_delta, rank_delta) = QUEEN_DIRECTIONS[direction];
+    if rank_delta < 0 || (rank_delta == 0 && file_delta > 0) {
+        squares.trailing_zeros() as usize
+    } else {
+        Board::NUM_SQUARES - 1 - squares.leading_zeros() as usize
+    }
+}
+
+/// the squares a slider on `square` reaches in the `QUEEN_DIRECTIONS` numbered
+/// `directions`, up to and including the first piece on `occupied`
+fn slider_attacks(square: usize, directions: Range<usize>, occupied: u64) -> u64 {
+    directions
+        .map(
+            |direction| match nearest_blocker(square, direction, occupied) {
+                Some(nearest) => RAYS[square][direction] & !RAYS[nearest][direction],
+                None => RAYS[square][direction],
+            },
+        )
+        .fold(0, |attacks, ray| attacks | ray)
+}
+
+/// pieces that aren't exactly one kind and pawns on their own back rank get no moves
+/// rather than crashing the engine
+fn gen_pseudo_legal_for_piece(pos: usize, piece: Piece, board: &Board, moves: &mut Vec<Move>) {
+    let color = piece.color();
+    let Some(us) = Color::from_bits(color) else {
+        return;
+    };
+    let targets = !board.occupancy(us);
+    match piece.kind() {
+        Piece::PAWN => gen_pawn_moves(pos, color, board, moves),
+        Piece::KNIGHT => step(pos, &KNIGHT_OFFSETS, targets, moves),
+        Piece::BISHOP => slide(pos, board, 4..8, targets, moves),
+        Piece::ROOK => slide(pos, board, 0..4, targets, moves),
+        Piece::QUEEN => slide(pos, board, 0..8, targets, moves),
+        Piece::KING => {
+            step(pos, &KING_OFFSETS, targets, moves);
+            gen_castle_moves(color, board, moves);
+        }
+        // no kind or several of them, most likely a corrupt piece. it can't move
+        _ => {}
+    }
+}
+
+fn gen_captures_for_piece(pos: usize, piece: Piece, board: &Board, moves: &mut Vec<Move>) {
+    let color = piece.color();
+    let Some(us) = Color::from_bits(color) else {
+        return;
+    };
+    let targets = board.occupancy(us.opposite());
+    match piece.kind() {
+        Piece::PAWN => gen_pawn_captures(pos, color, board, moves),
+        Piece::KNIGHT => step(pos, &KNIGHT_OFFSETS, targets, moves),
+        Piece::BISHOP => slide(pos, board, 4..8, targets, moves),
+        Piece::ROOK => slide(pos, board, 0..4, targets, moves),
+        Piece::QUEEN => slide(pos, board, 0..8, targets, moves),
+        Piece::KING => step(pos, &KING_OFFSETS, targets, moves),
+        // no kind or several of them, most likely a corrupt piece. it can't move
+        _ => {}
+    }
+}
+
+/// the moves of the slider on `pos` onto `targets` in the `QUEEN_DIRECTIONS` numbered
+/// `directions`, each ray as `slider_attacks` reaches it and nearest square first
+fn slide(pos: usize, board: &Board, directions: Range<usize>, targets: u64, moves: &mut Vec<Move>) {
+    let occupied = board.occupancy(Color::White) | board.occupancy(Color::Black);
+    for direction in directions {
+        let mut reached = slider_attacks(pos, direction..direction + 1, occupied) & targets;
+        while reached != 0 {
+            let target = nearest_square(reached, direction);
+            reached &= !(1 << target);
+            moves.push(Move::new_ab(pos, target));
+        }
+    }
+}
+
+/// pseudo-legal castles only check the castle rights and that the squares king and rook
+/// pass or land on are empty; moving out of or through check is up to the legal layer
+// the right alone isn't enough, boards built without a fen may have it without the king
+// and rook on their squares
+fn gen_castle_moves(color: u16, board: &Board, moves: &mut Vec<Move>) {
+    let occupied = board.occupancy(Color::White) | board.occupancy(Color::Black);
+    let span = |from: usize, to: usize| BETWEEN[from][to] | 1 << from | 1 << to;
+    for ty in CastleType::all() {
+        let (king_from, rook_from) = (board.castle_king_square(ty), board.castle_rook_square(ty));
+        // the castling pieces themselves don't block, whatever squares they end up on
+        let blockers = occupied & !(1 << king_from | 1 << rook_from);
+        if ty.color().bits() == color
+            && board.castle_rights() & ty.get_bit() != 0
+            && board[king_from] == Piece::new_unchecked(color, Piece::KING)
+            && board[rook_from] == Piece::new_unchecked(color, Piece::ROOK)
+            && (span(king_from, ty.king_to()) | span(rook_from, ty.rook_to())) & blockers == 0
+        {
+            moves.push(Move::new_castle(ty));
+        }
+    }
+}
+
+fn gen_pawn_moves(pos: usize, color: u16, board: &Board, moves: &mut Vec<Move>) {
+    let (rank_delta, pawn_rank, back_rank) = if color == Piece::WHITE {
+        (1, &Board::WHITE_PAWN_RANK, &Board::WHITE_BACK_RANK)
+    } else {
+        (-1, &Board::BLACK_PAWN_RANK, &Board::BLACK_BACK_RANK)
+    };
+    // fens and the other constructors reject such pawns, set_piece can still put one there
+    if back_rank.contains(&pos) {
+        return;
+    }
+    let ranks = Board::NUM_RANKS as i32;
+    let file = Board::file_of(pos) as i32;
+    let rank = Board::rank_of(pos) as i32 + rank_delta;
+    if !(0..ranks).contains(&rank) {
+        return;
+    }
+    let promotes = rank == 0 || rank == ranks - 1;
+    let target = Board::square_index(file as usize, rank as usize);
+    if board[target] == Piece::NO_PIECE {
+        push_pawn_move(pos, target, promotes, moves);
+        // offset() rather than index arithmetic, so the double advance can't leave the board
+        let double_advance = offset(pos, (0, 2 * rank_delta)).filter(|_| pawn_rank.contains(&pos));
+        if let Some(target) = double_advance {
+            if board[target] == Piece::NO_PIECE {
+                moves.push(Move::new_ab(pos, target));
+            }
+        }
+    }
+    gen_pawn_captures(pos, color, board, moves);
+}
+
+/// the diagonal captures of the pawn on `pos`, en passant included
+fn gen_pawn_captures(pos: usize, color: u16, board: &Board, moves: &mut Vec<Move>) {
+    let (rank_delta, back_rank) = if color == Piece::WHITE {
+        (1, &Board::WHITE_BACK_RANK)
+    } else {
+        (-1, &Board::BLACK_BACK_RANK)
+    };
+    if back_rank.contains(&pos) {
+        return;
+    }
+    let ranks = Board::NUM_RANKS as i32;
+    let rank = Board::rank_of(pos) as i32 + rank_delta;
+    let promotes = rank == 0 || rank == ranks - 1;
+    for file_delta in [-1, 1] {
+        let Some(target) = offset(pos, (file_delta, rank_delta)) else {
+            continue;
+        };
+        let p = board[target];
+        if p != Piece::NO_PIECE && p.color() != color {
+            push_pawn_move(pos, target, promotes, moves);
+        } else if board.en_passant_target() == Some(target) {
+            moves.push(Move::new_en_passant(
+                pos,
+                target,
+                Board::square_index(Board::file_of(target), Board::rank_of(pos)),
+            ));
+        }
+    }
+}
+
+fn push_pawn_move(from: usize, to: usize, promotes: bool, moves: &mut Vec<Move>) {
+    if promotes {
+        for piece in [Piece::QUEEN, Piece::ROOK, Piece::BISHOP, Piece::KNIGHT] {
+            moves.push(Move::new_promotion(from, to, piece));
+        }
+    } else {
+        moves.push(Move::new_ab(from, to));
+    }
+}
+
+/// the moves of the piece on `pos` one `offsets` step away onto `targets`
+fn step(pos: usize, offsets: &[(i32, i32)], targets: u64, moves: &mut Vec<Move>) {
+    for &o in offsets {
+        if let Some(target) = offset(pos, o).filter(|&t| targets & 1 << t != 0) {
+            moves.push(Move::new_ab(pos, target));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen;
+
+    fn square(name: &str) -> usize {
+        Board::square_from_name(name).unwrap()
+    }
+
+    /// the pseudo-legal moves of a lone `piece` on `name` on an otherwise empty board
+    fn lone_piece_moves(piece: u16, name: &str) -> Vec<Move> {
+        let mut board = Board::empty();
+        board.set_piece(square(name), Piece::new_unchecked(Piece::WHITE, piece));
+        board.moves_from(square(name))
+    }
+
+    #[test]
+    fn bishop_moves_on_empty_board() {
+        assert_eq!(lone_piece_moves(Piece::BISHOP, "d4").len(), 13);
+        assert_eq!(lone_piece_moves(Piece::BISHOP, "a1").len(), 7);
+        assert_eq!(lone_piece_moves(Piece::BISHOP, "h8").len(), 7);
+        // nothing wraps around from the a-file to the h-file
+        let targets = |moves: Vec<Move>| -> Vec<String> {
+            moves
+                .iter()
+                .map(|mv| mv.to_uci()[2..].to_string())
+                .collect()
+        };
+        let from_a4 = targets(lone_piece_moves(Piece::BISHOP, "a4"));
+        assert_eq!(from_a4.len(), 7);
+        assert!(from_a4.iter().all(|target| target.as_str() < "f"));
+    }
+
+    #[test]
+    fn bishop_stops_at_blockers() {
+        let board = fen::parse_board("4k3/8/8/2p5/3B4/4P3/8/4K3 w - - 0 1").unwrap();
+        let moves = board.moves_from(square("d4"));
+        let uci: Vec<String> = moves.iter().map(Move::to_uci).collect();
+        // the enemy pawn on c5 is taken, the own pawn on e3 blocks
+        assert!(uci.contains(&"d4c5".to_string()));
+        assert!(!uci.contains(&"d4b6".to_string()));
+        assert!(!uci.contains(&"d4e3".to_string()));
+        assert_eq!(moves.len(), 1 + 4 + 3);
+    }
+
+    #[test]
+    fn king_steps_on_empty_board() {
+        assert_eq!(lone_piece_moves(Piece::KING, "e4").len(), 8);
+        assert_eq!(lone_piece_moves(Piece::KING, "a1").len(), 3);
+        assert_eq!(lone_piece_moves(Piece::KING, "h8").len(), 3);
+        assert_eq!(lone_piece_moves(Piece::KING, "h4").len(), 5);
+        assert_eq!(lone_piece_moves(Piece::KING, "d1").len(), 5);
+        // stepping off the h-file doesn't land on the a-file
+        let moves = lone_piece_moves(Piece::KING, "h4");
+        assert!(moves.iter().all(|mv| !mv.to_uci()[2..].starts_with('a')));
+    }
+
+    fn castles(board: &Board, color: Color) -> Vec<Move> {
+        board
+            .gen_pseudo_legal(color)
+            .into_iter()
+            .filter(|mv| matches!(mv, Move::Castle { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn castling_needs_rights_and_empty_squares() {
+        let both = fen::parse_board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let white = [CastleType::WhiteLong, CastleType::WhiteShort].map(Move::new_castle);
+        assert_eq!(castles(&both, Color::White), white);
+        let blocked = fen::parse_board("r3k2r/8/8/8/8/8/8/R3KB1R w KQkq - 0 1").unwrap();
+        assert_eq!(
+            castles(&blocked, Color::White),
+            [Move::new_castle(CastleType::WhiteLong)]
+        );
+        let no_rights = fen::parse_board("r3k2r/8/8/8/8/8/8/R3K2R w kq - 0 1").unwrap();
+        assert!(castles(&no_rights, Color::White).is_empty());
+    }
+
+    #[test]
+    fn castling_needs_king_and_rook_in_place() {
+        let king = Piece::new_unchecked(Piece::WHITE, Piece::KING);
+        let rook = Piece::new_unchecked(Piece::WHITE, Piece::ROOK);
+        let black_king = Piece::new_unchecked(Piece::BLACK, Piece::KING);
+        let board = |pieces: &[(&str, Piece)]| {
+            let mut builder = Board::builder()
+                .piece(square("e8"), black_king)
+                .castle(CastleType::WhiteShort)
+                .castle(CastleType::WhiteLong);
+            for &(name, piece) in pieces {
+                builder = builder.piece(square(name), piece);
+            }
+            builder.build()
+        };
+        // the rights without a rook on h1 or a1
+        assert!(castles(&board(&[("e1", king)]), Color::White).is_empty());
+        // without a king on e1
+        assert!(castles(
+            &board(&[("d1", king), ("h1", rook), ("a1", rook)]),
+            Color::White
+        )
+        .is_empty());
+        assert_eq!(
+            castles(&board(&[("e1", king), ("h1", rook)]), Color::White),
+            [Move::new_castle(CastleType::WhiteShort)]
+        );
+    }
+
+    #[test]
+    fn perft_of_the_starting_position() {
+        let board = Board::default();
+        assert_eq!(board.perft(0), 1);
+        assert_eq!(board.perft(1), 20);
+        assert_eq!(board.perft(2), 400);
+        assert_eq!(board.perft(3), 8902);
+    }
+
+    #[test]
+    fn perft_divide_adds_up() {
+        let board = Board::default();
+        let divided = board.perft_divide(3);
+        assert_eq!(divided.len(), 20);
+        assert_eq!(divided.iter().map(|&(_, n)| n).sum::<u64>(), 8902);
+        let e4 = Move::from_uci("e2e4", &board).unwrap();
+        assert_eq!(divided.iter().find(|&&(mv, _)| mv == e4).unwrap().1, 600);
+    }
+
+    #[test]
+    fn castling_with_chess960_rooks() {
+        let mut board = fen::parse_board("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1").unwrap();
+        let white = [CastleType::WhiteLong, CastleType::WhiteShort].map(Move::new_castle);
+        assert_eq!(castles(&board, Color::White), white);
+        for (mv, fen) in [
+            (white[0], "1r2k1r1/8/8/8/8/8/8/2KR2R1 b gb - 1 1"),
+            (white[1], "1r2k1r1/8/8/8/8/8/8/1R3RK1 b gb - 1 1"),
+        ] {
+            let undo = board.make_move(mv);
+            assert_eq!(fen::to_fen(&board), fen);
+            board.unmake_move(mv, undo);
+            assert_eq!(
+                fen::to_fen(&board),
+                "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1"
+            );
+        }
+        // uci has the king take its own rook where the king's move alone doesn't tell
+        assert_eq!(Move::from_uci("e1g1", &board), Ok(white[1]));
+        assert_eq!(Move::from_uci("e1b1", &board), Ok(white[0]));
+        assert_eq!(Move::from_uci("e1c1", &board), Ok(white[0]));
+        let blocked = fen::parse_board("1r2k1r1/8/8/8/8/8/8/1RN1K1R1 w GBgb - 0 1").unwrap();
+        assert_eq!(castles(&blocked, Color::White), [white[1]]);
+        // the squares only have to be free of pieces other than the king and rook
+        let rook_stays = fen::parse_board("4k3/8/8/8/8/8/8/4KR2 w F - 0 1").unwrap();
+        assert_eq!(castles(&rook_stays, Color::White), [white[1]]);
+        let castled = rook_stays.with_move(white[1]);
+        assert_eq!(fen::to_fen(&castled), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+        let attacked = fen::parse_board("4kr2/8/8/8/8/8/8/1R2K1R1 w GB - 0 1").unwrap();
+        assert!(!attacked.is_legal(white[1]));
+        assert!(attacked.is_legal(white[0]));
+    }
+
+    #[test]
+    fn count_legal_moves_matches_gen_legal() {
+        for fen in [
+            fen::STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1",
+            // the en passant capture would expose the king along the rank
+            "8/8/8/8/k2Pp2Q/8/8/4K3 b - d3 0 1",
+        ] {
+            let mut board = fen::parse_board(fen).unwrap();
+            // follow the first few moves of each position to reach checks and pins too
+            for ply in 0..40 {
+                for color in [Color::White, Color::Black] {
+                    assert_eq!(
+                        board.count_legal_moves(color),
+                        board.gen_legal(color).len(),
+                        "{}",
+                        fen::to_fen(&board)
+                    );
+                }
+                let moves = board.gen_legal(board.turn());
+                if moves.is_empty() {
+                    break;
+                }
+                board.make_move(moves[ply * 5 % moves.len()]);
+            }
+        }
+    }
+
+    #[test]
+    fn pawns_on_their_own_back_rank_have_no_moves() {
+        let mut board = fen::parse_board("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        board.set_piece(
+            square("a1"),
+            Piece::new_unchecked(Piece::WHITE, Piece::PAWN),
+        );
+        board.set_piece(
+            square("b8"),
+            Piece::new_unchecked(Piece::BLACK, Piece::PAWN),
+        );
+        board.set_piece(
+            square("b2"),
+            Piece::new_unchecked(Piece::BLACK, Piece::ROOK),
+        );
+        assert!(board.moves_from(square("a1")).is_empty());
+        assert!(board.moves_from(square("b8")).is_empty());
+        assert!(board
+            .gen_captures(Color::White)
+            .iter()
+            .all(|mv| mv.to_uci() != "a1b2"));
+    }
+
+    #[test]
+    fn queen_moves_on_empty_board() {
+        assert_eq!(lone_piece_moves(Piece::QUEEN, "d4").len(), 27);
+        assert_eq!(lone_piece_moves(Piece::QUEEN, "a1").len(), 21);
+        assert_eq!(lone_piece_moves(Piece::QUEEN, "h8").len(), 21);
+    }
+
+    #[test]
+    fn queen_stops_at_blockers() {
+        let board = fen::parse_board("4k3/8/3p4/8/1P1Q4/8/5P2/4K3 w - - 0 1").unwrap();
+        let uci: Vec<String> = board
+            .moves_from(square("d4"))
+            .iter()
+            .map(Move::to_uci)
+            .collect();
+        // the enemy pawn on d6 is taken, the own pawns on b4 and f2 block
+        assert!(uci.contains(&"d4d6".to_string()));
+        assert!(!uci.contains(&"d4d7".to_string()));
+        assert!(uci.contains(&"d4c4".to_string()));
+        assert!(!uci.contains(&"d4b4".to_string()));
+        assert!(uci.contains(&"d4e3".to_string()));
+        assert!(!uci.contains(&"d4f2".to_string()));
+        assert_eq!(uci.len(), 27 - 2 - 2 - 2);
+    }
+
+    #[test]
+    fn rook_moves_along_ranks_and_files() {
+        assert_eq!(lone_piece_moves(Piece::ROOK, "d4").len(), 14);
+        assert_eq!(lone_piece_moves(Piece::ROOK, "a1").len(), 14);
+        let board = fen::parse_board("4k3/3p4/8/8/1P1R4/8/8/4K3 w - - 0 1").unwrap();
+        let mut uci: Vec<String> = board
+            .moves_from(square("d4"))
+            .iter()
+            .map(Move::to_uci)
+            .collect();
+        uci.sort();
+        assert_eq!(
+            uci,
+            [
+                "d4c4", "d4d1", "d4d2", "d4d3", "d4d5", "d4d6", "d4d7", "d4e4", "d4f4", "d4g4",
+                "d4h4"
+            ]
+        );
+    }
+
+    fn promotions(board: &Board, color: Color) -> Vec<String> {
+        let mut uci: Vec<String> = board
+            .gen_pseudo_legal(color)
+            .iter()
+            .filter(|mv| matches!(mv, Move::Promotion { .. }))
+            .map(Move::to_uci)
+            .collect();
+        uci.sort();
+        uci
+    }
+
+    #[test]
+    fn pawns_promote_to_every_piece() {
+        // straight ahead and by taking either rook
+        let board = fen::parse_board("3r1r2/4P3/8/8/8/8/8/k6K w - - 0 1").unwrap();
+        let uci = promotions(&board, Color::White);
+        assert_eq!(uci.len(), 12);
+        for target in ["e8", "d8", "f8"] {
+            for piece in ["q", "r", "b", "n"] {
+                assert!(uci.contains(&format!("e7{target}{piece}")));
+            }
+        }
+        let board = fen::parse_board("k6K/8/8/8/8/8/p7/8 b - - 0 1").unwrap();
+        assert_eq!(
+            promotions(&board, Color::Black),
+            ["a2a1b", "a2a1n", "a2a1q", "a2a1r"]
+        );
+        // a blocked pawn doesn't promote
+        let board = fen::parse_board("k3n2K/4P3/8/8/8/8/8/8 w - - 0 1").unwrap();
+        assert!(promotions(&board, Color::White).is_empty());
+    }
+
+    #[test]
+    fn legal_moves_respect_pins_and_checks() {
+        // the bishop on e2 is pinned along the file and can't move at all
+        let board = fen::parse_board("4r1k1/8/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+        assert!(board.legal_moves_from(square("e2")).is_empty());
+        assert!(!board.moves_from(square("e2")).is_empty());
+        // a rook pinned along the file still moves along it
+        let board = fen::parse_board("4r1k1/8/8/8/8/8/4R3/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.legal_moves_from(square("e2")).len(), 6);
+        // in check, only king steps off the file and blocking with the bishop remain
+        let board = fen::parse_board("4r1k1/8/8/8/8/8/2B5/4K3 w - - 0 1").unwrap();
+        let mut uci: Vec<String> = board
+            .gen_legal(Color::White)
+            .iter()
+            .map(Move::to_uci)
+            .collect();
+        uci.sort();
+        assert_eq!(uci, ["c2e4", "e1d1", "e1d2", "e1f1", "e1f2"]);
+    }
+
+    #[test]
+    fn square_attacks() {
+        let board = fen::parse_board("4k3/8/8/8/8/5n2/8/4K3 w - - 0 1").unwrap();
+        assert!(board.is_square_attacked(square("e1"), Color::Black));
+        assert!(!board.is_square_attacked(square("d1"), Color::Black));
+        // sliders are blocked by the first piece in the way
+        let board = fen::parse_board("4r1k1/8/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+        assert!(board.is_square_attacked(square("e2"), Color::Black));
+        assert!(!board.is_square_attacked(square("e1"), Color::Black));
+        // pawns only attack diagonally forward
+        let board = fen::parse_board("4k3/8/8/3p4/8/3P4/8/4K3 w - - 0 1").unwrap();
+        assert!(board.is_square_attacked(square("c4"), Color::Black));
+        assert!(board.is_square_attacked(square("e4"), Color::Black));
+        assert!(!board.is_square_attacked(square("d4"), Color::Black));
+        assert!(!board.is_square_attacked(square("e6"), Color::Black));
+        assert!(board.is_square_attacked(square("e4"), Color::White));
+        assert!(!board.is_square_attacked(square("c2"), Color::White));
+    }
+
+    #[test]
+    fn castling_out_of_and_through_check_is_illegal() {
+        let legal_castles = |fen| {
+            fen::parse_board(fen)
+                .unwrap()
+                .gen_legal(Color::White)
+                .into_iter()
+                .filter(|mv| matches!(mv, Move::Castle { .. }))
+                .count()
+        };
+        assert_eq!(legal_castles("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1"), 2);
+        assert_eq!(legal_castles("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1"), 1);
+        assert_eq!(legal_castles("4k3/8/8/8/8/8/3r4/R3K2R w KQ - 0 1"), 1);
+        assert_eq!(legal_castles("4k3/8/8/8/8/8/6r1/R3K2R w KQ - 0 1"), 1);
+        assert_eq!(legal_castles("4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1"), 0);
+        // the rook may pass an attacked square on the long side
+        assert_eq!(legal_castles("4k3/8/8/8/8/8/1r6/R3K2R w KQ - 0 1"), 2);
+    }
+
+    #[test]
+    fn check_detection() {
+        let board = fen::parse_board("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(board.is_check(Color::Black));
+        assert!(!board.is_check(Color::White));
+        let board = Board::default();
+        assert!(!board.is_check(Color::White));
+        assert!(!board.is_check(Color::Black));
+        // no king, no check
+        assert!(!Board::empty().is_check(Color::White));
+    }
+
+    #[test]
+    fn checkmate_and_stalemate() {
+        let fools_mate =
+            fen::parse_board("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+                .unwrap();
+        assert!(fools_mate.is_checkmate(Color::White));
+        assert!(!fools_mate.is_stalemate(Color::White));
+        assert_eq!(
+            fools_mate.status(),
+            GameStatus::Checkmate {
+                winner: Color::Black
+            }
+        );
+        let stalemate = fen::parse_board("5k2/5P2/5K2/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(stalemate.is_stalemate(Color::Black));
+        assert!(!stalemate.is_checkmate(Color::Black));
+        assert_eq!(stalemate.status(), GameStatus::Stalemate);
+        assert_eq!(Board::default().status(), GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn gen_captures_matches_the_legal_captures() {
+        let board = Board::default();
+        assert!(board.gen_captures(Color::White).is_empty());
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let board = fen::parse_board(fen).unwrap();
+            for color in [Color::White, Color::Black] {
+                let key = |mv: &Move| mv.to_uci();
+                let mut captures = board.gen_captures(color);
+                captures.sort_by_key(key);
+                let mut expected: Vec<Move> = board
+                    .gen_legal(color)
+                    .into_iter()
+                    .filter(|mv| mv.is_capture(&board))
+                    .collect();
+                expected.sort_by_key(key);
+                assert_eq!(captures, expected, "{fen}");
+            }
+        }
+    }
+
+    #[test]
+    fn moves_from_a_single_square() {
+        let board = Board::default();
+        let mut knight: Vec<String> = board
+            .moves_from(square("g1"))
+            .iter()
+            .map(Move::to_uci)
+            .collect();
+        knight.sort();
+        assert_eq!(knight, ["g1f3", "g1h3"]);
+        assert!(board.moves_from(square("h1")).is_empty());
+        assert!(board.moves_from(square("e4")).is_empty());
+        // black's pieces too, whoever's turn it is
+        assert_eq!(board.moves_from(square("e7")).len(), 2);
+        let rook = fen::parse_board("4k3/8/8/8/3R4/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(rook.moves_from(square("d4")).len(), 14);
+        // the moves of every piece together are all the moves
+        let all: Vec<Move> = board
+            .pieces_of(Color::White)
+            .flat_map(|(pos, _)| board.moves_from(pos))
+            .collect();
+        assert_eq!(all, board.gen_pseudo_legal(Color::White));
+    }
+
+    #[test]
+    fn en_passant_mustnt_expose_the_king() {
+        let en_passant = |fen| {
+            let board = fen::parse_board(fen).unwrap();
+            board
+                .gen_legal(board.turn())
+                .into_iter()
+                .any(|mv| matches!(mv, Move::EnPassant { .. }))
+        };
+        // both pawns leave the rank between the king and the queen
+        assert!(!en_passant("8/8/8/8/k2Pp2Q/8/8/4K3 b - d3 0 1"));
+        assert!(!en_passant("8/8/8/K2pP2q/8/8/8/4k3 w - d6 0 1"));
+        // with another piece in between it's fine
+        assert!(en_passant("8/8/8/8/k2Pp1NQ/8/8/4K3 b - d3 0 1"));
+        assert!(en_passant("8/8/8/8/3Pp3/8/8/k3K3 b - d3 0 1"));
+    }
+
+    #[test]
+    fn attackers_of_a_square() {
+        // a2 is attacked by the knight on c3 and the rook on a1
+        let board = fen::parse_board("4k3/8/8/8/8/2N5/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.attackers_to(square("a2"), Color::White),
+            [square("c3"), square("a1")]
+        );
+        assert!(board.attackers_to(square("a2"), Color::Black).is_empty());
+        // d2 by the knight on f3 and the king, the rook on a1 doesn't reach it
+        let board = fen::parse_board("4k3/8/8/8/8/5N2/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.attackers_to(square("d2"), Color::White),
+            [square("f3"), square("e1")]
+        );
+    }
+
+    #[test]
+    fn results_of_finished_games() {
+        let result = |fen| fen::parse_board(fen).unwrap().result();
+        assert_eq!(Board::default().result(), None);
+        assert_eq!(
+            result("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1"),
+            Some(GameResult::WhiteWins)
+        );
+        assert_eq!(
+            result("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"),
+            Some(GameResult::BlackWins)
+        );
+        assert_eq!(
+            result("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"),
+            Some(GameResult::Draw(DrawReason::Stalemate))
+        );
+        assert_eq!(
+            result("7k/8/6K1/8/8/8/8/6N1 b - - 0 1"),
+            Some(GameResult::Draw(DrawReason::InsufficientMaterial))
+        );
+        assert_eq!(
+            result("7k/8/6K1/8/8/8/8/6R1 b - - 100 80"),
+            Some(GameResult::Draw(DrawReason::FiftyMoves))
+        );
+        // mate on the hundredth half move still wins
+        assert_eq!(
+            result("7k/6Q1/6K1/8/8/8/8/8 b - - 100 80"),
+            Some(GameResult::WhiteWins)
+        );
+        let mut board = Board::default();
+        board
+            .apply_uci_moves(&[
+                "g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1", "f6g8",
+            ])
+            .unwrap();
+        assert_eq!(
+            board.result(),
+            Some(GameResult::Draw(DrawReason::Threefold))
+        );
+    }
+
+    #[test]
+    fn pawn_captures_dont_wrap_around_the_edges() {
+        let captures = |fen| -> Vec<String> {
+            let board = fen::parse_board(fen).unwrap();
+            let mut uci: Vec<String> = board
+                .gen_captures(board.turn())
+                .iter()
+                .map(Move::to_uci)
+                .collect();
+            uci.sort();
+            uci
+        };
+        assert_eq!(
+            captures("4k3/8/8/8/8/1p4p1/P6P/4K3 w - - 0 1"),
+            ["a2b3", "h2g3"]
+        );
+        assert_eq!(
+            captures("4k3/p6p/1P4P1/8/8/8/8/4K3 b - - 0 1"),
+            ["a7b6", "h7g6"]
+        );
+        // nothing on the other side of the board is in reach
+        assert!(captures("4k3/8/8/8/7p/p7/P6P/4K3 w - - 0 1").is_empty());
+    }
+
+    #[test]
+    fn is_legal_agrees_with_gen_legal() {
+        let board = Board::default();
+        assert!(board.is_legal(Move::from_uci("g1f3", &board).unwrap()));
+        // black's move, a knight move to the own pawn and one off the board
+        assert!(!board.is_legal(Move::from_uci("g8f6", &board).unwrap()));
+        assert!(!board.is_legal(Move::new_ab(square("g1"), square("e2"))));
+        assert!(!board.is_legal(Move::new_ab(square("g1"), 64)));
+        // the knight is pinned to the king
+        let board = fen::parse_board("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert!(!board.is_legal(Move::from_uci("e2c3", &board).unwrap()));
+        assert!(board.is_legal(Move::from_uci("e1d1", &board).unwrap()));
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let board = fen::parse_board(fen).unwrap();
+            let legal = board.gen_legal(board.turn());
+            for mv in board.gen_pseudo_legal(board.turn()) {
+                assert_eq!(board.is_legal(mv), legal.contains(&mv), "{fen} {mv}");
+            }
+        }
+    }
+
+    #[test]
+    fn gives_check_after_the_move() {
+        let board = fen::parse_board("4k3/8/8/8/8/8/8/R3K1N1 w - - 0 1").unwrap();
+        assert!(board.gives_check(Move::from_uci("a1a8", &board).unwrap()));
+        assert!(!board.gives_check(Move::from_uci("a1a7", &board).unwrap()));
+        assert!(!board.gives_check(Move::from_uci("g1f3", &board).unwrap()));
+        // discovered by moving the knight out of the rook's way
+        let board = fen::parse_board("4k3/8/8/8/8/8/4N3/4R1K1 w - - 0 1").unwrap();
+        assert!(board.gives_check(Move::from_uci("e2c3", &board).unwrap()));
+        assert!(board.gives_check(Move::from_uci("e2d4", &board).unwrap()));
+    }
+
+    #[test]
+    fn legal_moves_from_a_square() {
+        // the bishop on d2 is pinned by the queen on a5 and only moves along the pin
+        let board = fen::parse_board("4k3/8/8/q7/8/8/3B4/4K1N1 w - - 0 1").unwrap();
+        let mut bishop: Vec<String> = board
+            .legal_moves_from(square("d2"))
+            .iter()
+            .map(Move::to_uci)
+            .collect();
+        bishop.sort();
+        assert_eq!(bishop, ["d2a5", "d2b4", "d2c3"]);
+        // the free knight has all of its moves
+        assert_eq!(board.legal_moves_from(square("g1")).len(), 3);
+        assert!(board.legal_moves_from(square("e4")).is_empty());
+        // a bishop pinned along a file can't move at all
+        let board = fen::parse_board("4k3/8/8/8/4r3/8/4B3/4K3 w - - 0 1").unwrap();
+        assert!(board.legal_moves_from(square("e2")).is_empty());
+    }
+
+    #[test]
+    fn positions_with_the_side_that_moved_in_check_are_illegal() {
+        // black to move but white's king is attacked by the rook
+        let illegal = fen::parse_board("4k3/8/8/8/8/8/4r3/4K3 b - - 0 1").unwrap();
+        assert!(!illegal.is_legal_position());
+        let legal = fen::parse_board("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1").unwrap();
+        assert!(legal.is_legal_position());
+        assert!(Board::default().is_legal_position());
+    }
+
+    #[test]
+    fn odd_pieces_have_no_moves() {
+        let mut board = Board::default();
+        board.set_piece(
+            square("e4"),
+            Piece::from(Piece::WHITE | Piece::KNIGHT | Piece::BISHOP),
+        );
+        board.set_piece(square("d4"), Piece::from(Piece::WHITE));
+        assert!(board.moves_from(square("e4")).is_empty());
+        assert!(board.legal_moves_from(square("e4")).is_empty());
+        assert!(board.moves_from(square("d4")).is_empty());
+        // they still block the pawns in front of them
+        assert_eq!(board.gen_legal(Color::White).len(), 20 - 2);
+    }
+
+    #[test]
+    fn double_pushes_only_from_the_starting_rank() {
+        let moves = |fen, name| -> Vec<String> {
+            let board = fen::parse_board(fen).unwrap();
+            let mut uci: Vec<String> = board
+                .moves_from(square(name))
+                .iter()
+                .map(Move::to_uci)
+                .collect();
+            uci.sort();
+            uci
+        };
+        assert_eq!(
+            moves("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", "e2"),
+            ["e2e3", "e2e4"]
+        );
+        assert!(moves("4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1", "e2").is_empty());
+        assert_eq!(moves("4k3/8/8/8/4n3/8/4P3/4K3 w - - 0 1", "e2"), ["e2e3"]);
+        assert_eq!(moves("4k3/8/8/8/8/4P3/8/4K3 w - - 0 1", "e3"), ["e3e4"]);
+        assert_eq!(
+            moves("4k3/4p3/8/8/8/8/8/4K3 b - - 0 1", "e7"),
+            ["e7e5", "e7e6"]
+        );
+        assert_eq!(moves("4k3/8/4p3/8/8/8/8/4K3 b - - 0 1", "e6"), ["e6e5"]);
+        assert!(moves("4k3/4p3/4N3/8/8/8/8/4K3 b - - 0 1", "e7").is_empty());
+    }
+
+    #[test]
+    fn underpromotions_can_be_left_out() {
+        let board = fen::parse_board("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let count = |opts| {
+            board
+                .gen_legal_opts(Color::White, opts)
+                .into_iter()
+                .filter(|mv| matches!(mv, Move::Promotion { .. }))
+                .count()
+        };
+        assert_eq!(count(GenOpts::default()), 8);
+        let queens_only = GenOpts {
+            underpromotions: false,
+        };
+        assert_eq!(count(queens_only), 2);
+        assert!(board
+            .gen_legal_opts(Color::White, queens_only)
+            .iter()
+            .all(|mv| !matches!(mv, Move::Promotion { piece, .. } if *piece != Piece::QUEEN)));
+    }
+
+    #[test]
+    fn attacked_squares_of_the_start() {
+        let board = Board::default();
+        // the third rank, the whole second one covered by the pieces behind it,
+        // and the first one but the corners
+        let expected = (40..=62).fold(0u64, |bits, i| bits | 1 << i) & !(1 << square("a1"));
+        assert_eq!(board.attacked_squares(Color::White), expected);
+        for color in [Color::White, Color::Black] {
+            let attacked = board.attacked_squares(color);
+            for i in 0..64 {
+                assert_eq!(attacked & 1 << i != 0, board.is_square_attacked(i, color));
+            }
+        }
+    }
+
+    #[test]
+    fn detailed_perft_of_kiwipete() {
+        let board = fen::parse_board(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let stats =
+            |nodes, captures, en_passant, castles, promotions, checks, checkmates| PerftStats {
+                nodes,
+                captures,
+                en_passant,
+                castles,
+                promotions,
+                checks,
+                checkmates,
+            };
+        assert_eq!(board.perft_detailed(1), stats(48, 8, 0, 2, 0, 0, 0));
+        assert_eq!(board.perft_detailed(2), stats(2039, 351, 1, 91, 0, 3, 0));
+        assert_eq!(board.perft_detailed(2).nodes, board.perft(2));
+    }
+
+    #[test]
+    fn checkers_of_single_and_double_checks() {
+        let board = fen::parse_board("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").unwrap();
+        assert_eq!(board.checkers(Color::White), vec![square("a1")]);
+        assert!(board.checkers(Color::Black).is_empty());
+        // the rook on e4 and the knight on d3
+        let board = fen::parse_board("4k3/8/8/8/4r3/3n4/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.checkers(Color::White), vec![36, 43]);
+        // only the king can get out of a double check
+        let moves = board.gen_legal(Color::White);
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|mv| mv.to_uci().starts_with("e1")));
+        assert!(Board::default().checkers(Color::White).is_empty());
+    }
+
+    #[test]
+    fn evasions_match_the_filtered_pseudo_legal_moves() {
+        for fen in [
+            // a rook check that can be blocked, captured or stepped out of
+            "4k3/8/8/8/8/2n5/1B6/r3K1N1 w - - 0 1",
+            // a knight check, only capturing it or moving the king helps
+            "r3k2r/8/8/8/8/3n4/8/R3K2R w KQkq - 0 1",
+            // double check
+            "4k3/8/8/8/4r3/3n4/8/4K3 w - - 0 1",
+            // the checking pawn can be taken en passant
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+            // a bishop check from afar, the knight and the rook can block
+            "4k3/8/8/b7/8/8/2N5/3RK3 w - - 0 1",
+        ] {
+            let board = fen::parse_board(fen).unwrap();
+            let color = board.turn();
+            assert!(board.is_check(color), "{fen}");
+            let naive: Vec<Move> = board
+                .gen_pseudo_legal(color)
+                .into_iter()
+                .filter(|mv| !matches!(mv, Move::Castle { .. }))
+                .filter(|&mv| !board.with_move(mv).is_check(color))
+                .collect();
+            assert_eq!(board.gen_legal(color), naive, "{fen}");
+        }
+    }
+
+    #[test]
+    fn en_passant_captures_name_the_pawn_they_take() {
+        let board = fen::parse_board("4k3/8/8/4Pp2/8/8/8/4K3 w - f6 0 1").unwrap();
+        let en_passant: Vec<Move> = board
+            .gen_legal(Color::White)
+            .into_iter()
+            .filter(|mv| matches!(mv, Move::EnPassant { .. }))
+            .collect();
+        assert_eq!(
+            en_passant,
+            vec![Move::new_en_passant(
+                square("e5"),
+                square("f6"),
+                square("f5")
+            )]
+        );
+        let after = board.with_move(en_passant[0]);
+        assert_eq!(after[square("f5")], Piece::NO_PIECE);
+        assert_eq!(
+            after[square("f6")],
+            Piece::new_unchecked(Piece::WHITE, Piece::PAWN)
+        );
+        // and for black, the pawn a rank above the target
+        let board = fen::parse_board("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        assert!(board
+            .gen_legal(Color::Black)
+            .contains(&Move::new_en_passant(
+                square("d4"),
+                square("e3"),
+                square("e4")
+            )));
+    }
+
+    #[test]
+    fn legal_moves_in_san() {
+        let board =
+            fen::parse_board("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4")
+                .unwrap();
+        let moves = board.legal_moves_san();
+        assert_eq!(moves.len(), board.gen_legal(Color::White).len());
+        assert!(moves.contains(&"Qxf7#".to_string()));
+        assert!(moves.contains(&"Bxf7+".to_string()));
+        assert!(moves.contains(&"Qxe5+".to_string()));
+        // pawns come first, by their target squares from a8 to h1, and the king last
+        assert_eq!(moves[0], "a4");
+        assert_eq!(moves.last().map(String::as_str), Some("Kf1"));
+        assert_eq!(board.legal_moves_san(), moves);
+    }
+
+    #[test]
+    fn validate_move_rejects_malformed_moves() {
+        let board = Board::default();
+        let error = |mv| board.validate_move(mv).unwrap_err();
+        assert_eq!(
+            error(Move::new_ab(square("e4"), square("e5"))),
+            "no piece on e4"
+        );
+        assert_eq!(
+            error(Move::new_ab(square("e2"), 64)),
+            "square index 64 is off the board"
+        );
+        assert_eq!(
+            error(Move::new_ab(100, square("e4"))),
+            "square index 100 is off the board"
+        );
+        assert_eq!(
+            error(Move::new_ab(square("e7"), square("e5"))),
+            "the piece on e7 isn't White's"
+        );
+        assert_eq!(
+            error(Move::new_ab(square("a1"), square("a2"))),
+            "a2 is taken by one of White's own pieces"
+        );
+        assert_eq!(
+            error(Move::new_castle(CastleType::BlackShort)),
+            "BlackShort castling isn't White's"
+        );
+        assert_eq!(
+            error(Move::new_promotion(
+                square("e2"),
+                square("e3"),
+                Piece::QUEEN
+            )),
+            "e3 isn't on the last rank"
+        );
+        // well-formed, though not legal
+        assert!(board
+            .validate_move(Move::new_ab(square("d1"), square("d4")))
+            .is_ok());
+        assert!(board
+            .validate_move(Move::new_castle(CastleType::WhiteShort))
+            .is_ok());
+        assert!(!board.is_legal(Move::new_castle(CastleType::WhiteShort)));
+    }
+
+    #[test]
+    fn gen_legal_keeps_its_documented_order() {
+        let board = fen::parse_board("4k3/1P6/8/8/8/8/6N1/R3K3 w Q - 0 1").unwrap();
+        let moves: Vec<String> = board
+            .gen_legal(Color::White)
+            .iter()
+            .map(Move::to_uci)
+            .collect();
+        // by the square moved from, a8 to h1, then by direction and distance.
+        // promotions go queen to knight and castles come after the king's steps
+        let expected = [
+            "b7b8q", "b7b8r", "b7b8b", "b7b8n", "g2e3", "g2f4", "g2h4", "a1a2", "a1a3", "a1a4",
+            "a1a5", "a1a6", "a1a7", "a1a8", "a1b1", "a1c1", "a1d1", "e1d1", "e1f1", "e1d2", "e1e2",
+            "e1f2", "e1c1",
+        ];
+        assert_eq!(moves, expected);
+    }
+
+    #[test]
+    fn sliders_walk_their_rays_to_the_first_piece() {
+        // the squares a slider on `from` reaches, walking one square at a time
+        let walk = |board: &Board, from: usize, directions: &[(i32, i32)]| {
+            let mut targets = Vec::new();
+            for &(df, dr) in directions {
+                let (mut file, mut rank) =
+                    (Board::file_of(from) as i32, Board::rank_of(from) as i32);
+                loop {
+                    file += df;
+                    rank += dr;
+                    if !(0..8).contains(&file) || !(0..8).contains(&rank) {
+                        break;
+                    }
+                    let to = Board::square_index(file as usize, rank as usize);
+                    if board[to].color() == board[from].color() {
+                        break;
+                    }
+                    targets.push(to);
+                    if board[to] != Piece::NO_PIECE {
+                        break;
+                    }
+                }
+            }
+            targets.sort();
+            targets
+        };
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "4k3/8/3p4/8/1P1Q4/8/5P2/4K3 w - - 0 1",
+            "Q3k2B/8/8/8/8/8/8/R3K2R w - - 0 1",
+        ] {
+            let board = fen::parse_board(fen).unwrap();
+            for (from, p) in board.pieces_of(Color::White) {
+                let directions: &[(i32, i32)] = match p.kind() {
+                    Piece::ROOK => &QUEEN_DIRECTIONS[..4],
+                    Piece::BISHOP => &QUEEN_DIRECTIONS[4..],
+                    Piece::QUEEN => &QUEEN_DIRECTIONS,
+                    _ => continue,
+                };
+                let mut targets: Vec<usize> = board
+                    .gen_pseudo_legal(Color::White)
+                    .into_iter()
+                    .filter_map(|mv| match mv {
+                        Move::AB { from: f, to } if f == from => Some(to),
+                        _ => None,
+                    })
+                    .collect();
+                targets.sort();
+                assert_eq!(targets, walk(&board, from, directions), "{fen} from {from}");
+            }
+        }
+    }
+
+    #[test]
+    fn chess960_kings_castle_from_their_own_file() {
+        let board = fen::parse_board("4k3/8/8/8/8/8/8/R4K1R w HA - 0 1").unwrap();
+        let short = Move::new_castle(CastleType::WhiteShort);
+        let long = Move::new_castle(CastleType::WhiteLong);
+        assert_eq!(castles(&board, Color::White), vec![long, short]);
+        assert_eq!(Move::from_uci("f1h1", &board), Ok(short));
+        assert_eq!(Move::from_uci("f1a1", &board), Ok(long));
+        assert_eq!(
+            fen::to_fen(&board.with_move(short)),
+            "4k3/8/8/8/8/8/8/R4RK1 b - - 1 1"
+        );
+        assert_eq!(
+            fen::to_fen(&board.with_move(long)),
+            "4k3/8/8/8/8/8/8/2KR3R b - - 1 1"
+        );
+        let mut played = board.clone();
+        let undo = played.make_move(long);
+        played.unmake_move(long, undo);
+        assert_eq!(played, board);
+        // the long castle crosses e1, which the rook on e8 attacks, the short one doesn't
+        let board = fen::parse_board("4rk2/8/8/8/8/8/8/R4K1R w HA - 0 1").unwrap();
+        assert!(board.is_legal(short));
+        assert!(!board.is_legal(long));
+        assert!(!board.gen_legal(Color::White).contains(&long));
+        assert_eq!(
+            board.count_legal_moves(Color::White),
+            board.gen_legal(Color::White).len()
+        );
+    }
+}
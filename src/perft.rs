@@ -0,0 +1,253 @@
+use std::time::Instant;
+
+use crate::chess::{Board, Color, Piece};
+use crate::fen;
+use crate::move_gen::{offset, KING_OFFSETS, KNIGHT_OFFSETS, QUEEN_DIRECTIONS};
+use crate::r#move::Move;
+
+/// well known positions with their published perft node counts at depths 1, 2 and 3.
+/// between them they cover castling, en passant, promotions and pins
+pub const SUITE: [(&str, &str, [u64; 3]); 6] = [
+    ("start", fen::STARTING_FEN, [20, 400, 8902]),
+    (
+        "kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        [48, 2039, 97862],
+    ),
+    (
+        "position 3",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        [14, 191, 2812],
+    ),
+    (
+        "position 4",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        [6, 264, 9467],
+    ),
+    (
+        "position 5",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        [44, 1486, 62379],
+    ),
+    (
+        "position 6",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        [46, 2079, 89890],
+    ),
+];
+
+/// smaller positions from a published collection of move generation edge cases, with
+/// the node count of a reference engine at one depth each: discovered checks, en passant
+/// out of check, castling through attacked squares, promotions and stalemate traps
+pub const EDGE_CASES: [(&str, u32, u64); 21] = [
+    ("r6r/1b2k1bq/8/8/7B/8/8/R3K2R b KQ - 3 2", 1, 8),
+    ("8/8/8/2k5/2pP4/8/B7/4K3 b - d3 0 3", 1, 8),
+    (
+        "r1bqkbnr/pppppppp/n7/8/8/P7/1PPPPPPP/RNBQKBNR w KQkq - 2 2",
+        1,
+        19,
+    ),
+    (
+        "r3k2r/p1pp1pb1/bn2Qnp1/2qPN3/1p2P3/2N5/PPPBBPPP/R3K2R b KQkq - 3 2",
+        1,
+        5,
+    ),
+    (
+        "2kr3r/p1ppqpb1/bn2Qnp1/3PN3/1p2P3/2N5/PPPBBPPP/R3K2R b KQ - 3 2",
+        1,
+        44,
+    ),
+    (
+        "rnb2k1r/pp1Pbppp/2p5/q7/2B5/8/PPPQNnPP/RNB1K2R w KQ - 3 9",
+        1,
+        39,
+    ),
+    ("2r5/3pk3/8/2P5/8/2K5/8/8 w - - 5 4", 1, 9),
+    ("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", 6, 1_134_888),
+    ("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", 6, 1_015_133),
+    ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 6, 1_440_467),
+    ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", 6, 661_072),
+    ("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", 6, 803_711),
+    ("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", 4, 1_274_206),
+    ("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", 4, 1_720_476),
+    ("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 6, 3_821_001),
+    ("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", 5, 1_004_658),
+    ("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", 6, 217_342),
+    ("8/P1k5/K7/8/8/8/8/8 w - - 0 1", 6, 92_683),
+    ("K1k5/8/P7/8/8/8/8/8 w - - 0 1", 6, 2217),
+    ("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", 7, 567_584),
+    ("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", 4, 23_527),
+];
+
+/// compares the perft of `fen` at `depth` with `expected`
+pub fn check(fen: &str, depth: u32, expected: u64) -> Result<(), String> {
+    let nodes = fen::parse_board(fen)?.perft(depth);
+    if nodes == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "perft({depth}) of '{fen}' is {nodes}, {expected} expected"
+        ))
+    }
+}
+
+/// `Board::perft`, but with the pieces and their king found by scanning the squares and
+/// the king's safety checked by walking out from it, as before the board had bitboards.
+/// kept as the baseline `run_suite` measures the bitboards against
+fn perft_by_scan(board: &mut Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let us = board.turn();
+    let king = Piece::new_unchecked(us.bits(), Piece::KING);
+    let mut nodes = 0;
+    for pos in 0..Board::NUM_SQUARES {
+        if board[pos].color() != us.bits() {
+            continue;
+        }
+        for mv in board.moves_from(pos) {
+            // castling out of or through check takes more than the king being safe after it
+            if matches!(mv, Move::Castle { .. }) && !board.is_legal(mv) {
+                continue;
+            }
+            let undo = board.make_move(mv);
+            let king_square = (0..Board::NUM_SQUARES).find(|&sq| board[sq] == king);
+            if king_square.is_some_and(|sq| !attacked_by_scan(board, sq, us.opposite())) {
+                nodes += perft_by_scan(board, depth - 1);
+            }
+            board.unmake_move(mv, undo);
+        }
+    }
+    nodes
+}
+
+/// whether a piece of `by` attacks `square`, walking out from it over the squares
+fn attacked_by_scan(board: &Board, square: usize, by: Color) -> bool {
+    let is = |sq: usize, kinds: &[u16]| {
+        board[sq].color() == by.bits() && kinds.contains(&board[sq].kind())
+    };
+    let steps = |offsets: &[(i32, i32)], kind: u16| {
+        offsets
+            .iter()
+            .filter_map(|&o| offset(square, o))
+            .any(|sq| is(sq, &[kind]))
+    };
+    // pawns attack from one rank behind the square, seen from their side
+    let pawn_rank_delta = if by == Color::White { -1 } else { 1 };
+    let pawns = [(-1, pawn_rank_delta), (1, pawn_rank_delta)];
+    let sliders = QUEEN_DIRECTIONS.iter().enumerate().any(|(i, &direction)| {
+        let kinds = if i < 4 {
+            [Piece::ROOK, Piece::QUEEN]
+        } else {
+            [Piece::BISHOP, Piece::QUEEN]
+        };
+        let mut target = offset(square, direction);
+        while let Some(sq) = target {
+            if board[sq] != Piece::NO_PIECE {
+                return is(sq, &kinds);
+            }
+            target = offset(sq, direction);
+        }
+        false
+    });
+    steps(&KNIGHT_OFFSETS, Piece::KNIGHT)
+        || steps(&KING_OFFSETS, Piece::KING)
+        || steps(&pawns, Piece::PAWN)
+        || sliders
+}
+
+/// checks every position of `SUITE` at every depth, printing a line per position
+/// with the time it took, as a rough benchmark of the move generation, then times
+/// the deepest `SUITE` perfts against `perft_by_scan` and checks every one of
+/// `EDGE_CASES`
+pub fn run_suite() -> Result<(), String> {
+    for (name, fen, counts) in SUITE {
+        let start = Instant::now();
+        for (depth, expected) in (1..).zip(counts) {
+            check(fen, depth, expected)?;
+        }
+        let nodes: u64 = counts.iter().sum();
+        println!(
+            "{name}: ok, {nodes} nodes in {:.1} ms",
+            start.elapsed().as_secs_f64() * 1000.0
+        );
+    }
+    for (name, fen, counts) in SUITE {
+        let board = fen::parse_board(fen)?;
+        let depth = counts.len() as u32;
+        let start = Instant::now();
+        let nodes = board.perft(depth);
+        let bitboards = start.elapsed().as_secs_f64();
+        let start = Instant::now();
+        let scanned = perft_by_scan(&mut board.clone(), depth);
+        let scanning = start.elapsed().as_secs_f64();
+        if scanned != nodes {
+            return Err(format!(
+                "{name}: perft({depth}) is {nodes} with bitboards, {scanned} scanning the squares"
+            ));
+        }
+        println!(
+            "{name} perft({depth}): {:.1} ms with bitboards, {:.1} ms scanning the squares, {:.1}x faster",
+            bitboards * 1000.0,
+            scanning * 1000.0,
+            scanning / bitboards
+        );
+    }
+    let start = Instant::now();
+    for (fen, depth, expected) in EDGE_CASES {
+        check(fen, depth, expected)?;
+    }
+    println!(
+        "{} edge cases: ok in {:.1} ms",
+        EDGE_CASES.len(),
+        start.elapsed().as_secs_f64() * 1000.0
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// edge cases with more nodes than this are left to `cargo test -- --ignored`,
+    /// they take too long in unoptimized builds
+    const QUICK_NODES: u64 = 100_000;
+
+    #[test]
+    fn suite() {
+        for (name, fen, counts) in SUITE {
+            for (depth, expected) in (1..).zip(counts) {
+                check(fen, depth, expected).unwrap_or_else(|e| panic!("{name}: {e}"));
+            }
+        }
+    }
+
+    #[test]
+    fn scanning_the_squares_counts_the_same() {
+        for (name, fen, counts) in SUITE {
+            let mut board = fen::parse_board(fen).unwrap();
+            for (depth, expected) in (1..).zip(counts) {
+                assert_eq!(
+                    perft_by_scan(&mut board, depth),
+                    expected,
+                    "{name} at {depth}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn edge_cases() {
+        for (fen, depth, expected) in EDGE_CASES.into_iter().filter(|e| e.2 <= QUICK_NODES) {
+            check(fen, depth, expected).unwrap();
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn deep_edge_cases() {
+        for (fen, depth, expected) in EDGE_CASES.into_iter().filter(|e| e.2 > QUICK_NODES) {
+            check(fen, depth, expected).unwrap();
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use crate::chess::{Board, Color};
+use crate::fen;
+use crate::r#move::Move;
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// the tags every pgn game is supposed to have, in their required order, with the
+/// values to use for unknown ones
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// movetext lines are wrapped before getting longer than this
+const MAX_LINE_LENGTH: usize = 80;
+
+/// the tag pairs and moves of a single pgn game. the moves are resolved against the
+/// position they're played in, starting from the FEN tag if there is one.
+/// comments, variations, NAGs, move numbers and the result are skipped
+pub fn parse_game(pgn: &str) -> Result<(HashMap<String, String>, Vec<Move>), String> {
+    let mut tags = HashMap::new();
+    let mut movetext = String::new();
+    for line in pgn.lines() {
+        let line = line.trim();
+        match line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            Some(tag) => {
+                let (name, value) = tag
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| format!("illegal tag pair '{line}'"))?;
+                let value = value
+                    .trim()
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .ok_or_else(|| format!("tag value expected in quotes in '{line}'"))?;
+                tags.insert(name.to_string(), value.replace("\\\"", "\""));
+            }
+            None => {
+                // a semicolon comments out the rest of the line
+                movetext.push_str(line.split(';').next().unwrap());
+                movetext.push(' ');
+            }
+        }
+    }
+
+    let mut board = match tags.get("FEN") {
+        Some(fen) => fen::parse_board(fen)?,
+        None => Board::default(),
+    };
+    let mut moves = Vec::new();
+    for token in tokenize(&movetext)? {
+        // move numbers may be glued to the move, as in 1.e4 or 12...Nf6
+        let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        if san.is_empty() || san.starts_with('$') || RESULTS.contains(&token) {
+            continue;
+        }
+        let mv = Move::from_san(san, &board)
+            .map_err(|e| format!("move {} of the game: {e}", moves.len() + 1))?;
+        board.make_move(mv);
+        moves.push(mv);
+    }
+    Ok((tags, moves))
+}
+
+/// the whitespace separated tokens of `movetext` outside of {comments} and (variations)
+fn tokenize(movetext: &str) -> Result<Vec<&str>, String> {
+    let mut tokens = Vec::new();
+    let mut in_comment = false;
+    let mut variation_depth = 0;
+    let mut start = None;
+    for (i, c) in movetext.char_indices() {
+        let skipping = in_comment || variation_depth > 0;
+        let separates = c.is_whitespace() || matches!(c, '{' | '}' | '(' | ')');
+        if separates {
+            if let Some(s) = start.take() {
+                tokens.push(&movetext[s..i]);
+            }
+        }
+        match c {
+            '{' if !in_comment => in_comment = true,
+            '}' if in_comment => in_comment = false,
+            '(' if !in_comment => variation_depth += 1,
+            ')' if !in_comment => {
+                if variation_depth == 0 {
+                    return Err(String::from("unmatched ')' in pgn movetext"));
+                }
+                variation_depth -= 1;
+            }
+            _ if !separates && !skipping && start.is_none() => start = Some(i),
+            _ => {}
+        }
+    }
+    if in_comment || variation_depth > 0 {
+        return Err(String::from(
+            "unterminated comment or variation in pgn movetext",
+        ));
+    }
+    if let Some(s) = start {
+        tokens.push(&movetext[s..]);
+    }
+    Ok(tokens)
+}
+
+/// the pgn of `moves` played from `start`. unless `tags` is empty, the seven tag roster
+/// comes first, followed by the other tags in alphabetical order. games that don't
+/// start from the starting position get SetUp and FEN tags
+pub fn write_game(start: &Board, moves: &[Move], tags: &HashMap<String, String>) -> String {
+    let mut tags = tags.clone();
+    if *start != Board::default() && !tags.contains_key("FEN") {
+        tags.insert(String::from("SetUp"), String::from("1"));
+        tags.insert(String::from("FEN"), fen::to_fen(start));
+    }
+    let result = tags.get("Result").map_or("*", |r| r.as_str()).to_string();
+
+    let mut pgn = String::new();
+    if !tags.is_empty() {
+        for (name, default) in SEVEN_TAG_ROSTER {
+            let value = tags.remove(name);
+            pgn.push_str(&tag_pair(name, value.as_deref().unwrap_or(default)));
+        }
+        let mut rest: Vec<_> = tags.into_iter().collect();
+        rest.sort();
+        for (name, value) in rest {
+            pgn.push_str(&tag_pair(&name, &value));
+        }
+        pgn.push('\n');
+    }
+
+    let mut tokens = Vec::new();
+    let mut board = start.clone();
+    for (i, &mv) in moves.iter().enumerate() {
+        if board.turn() == Color::White {
+            tokens.push(format!("{}.", board.fullmove_number()));
+        } else if i == 0 {
+            tokens.push(format!("{}...", board.fullmove_number()));
+        }
+        tokens.push(mv.to_san(&board));
+        board.make_move(mv);
+    }
+    tokens.push(result);
+
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+            pgn.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            pgn.push(' ');
+            line_length += 1;
+        }
+        line_length += token.len();
+        pgn.push_str(&token);
+    }
+    pgn.push('\n');
+    pgn
+}
+
+fn tag_pair(name: &str, value: &str) -> String {
+    format!("[{name} \"{}\"]\n", value.replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPERA_GAME: &str = r#"[Event "Paris"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[White "Paul Morphy"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 {a poor move} 4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6
+7. Qb3 Qe7 8. Nc3 c6 9. Bg5 b5?! (9... Qb4+ 10. Qxb4) 10. Nxb5 cxb5 11. Bxb5+ Nbd7
+12. O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6 $6 15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# 1-0"#;
+
+    #[test]
+    fn parses_an_annotated_game() {
+        let (tags, moves) = parse_game(OPERA_GAME).unwrap();
+        assert_eq!(tags["White"], "Paul Morphy");
+        assert_eq!(tags["Result"], "1-0");
+        assert_eq!(moves.len(), 33);
+        let mut board = Board::default();
+        for mv in moves {
+            board.make_move(mv);
+        }
+        assert_eq!(
+            fen::to_fen(&board),
+            "1n1Rkb1r/p4ppp/4q3/4p1B1/4P3/8/PPP2PPP/2K5 b k - 1 17"
+        );
+    }
+
+    #[test]
+    fn rejects_illegal_movetext() {
+        assert!(parse_game("1. e4 e5 2. Ke3").is_err());
+        assert!(parse_game("1. e4 {unterminated").is_err());
+        assert!(parse_game("1. e4 e5)").is_err());
+    }
+
+    #[test]
+    fn writes_numbered_movetext() {
+        let (_, moves) = parse_game("1. e4 e5 2. Nf3 Nc6").unwrap();
+        let pgn = write_game(&Board::default(), &moves, &HashMap::new());
+        assert_eq!(pgn, "1. e4 e5 2. Nf3 Nc6 *\n");
+
+        let mut board = Board::default();
+        board.apply_uci_moves(&["e2e4"]).unwrap();
+        let mv = Move::from_uci("c7c5", &board).unwrap();
+        let tags = HashMap::from([(String::from("White"), String::from("Alice"))]);
+        let pgn = write_game(&board, &[mv], &tags);
+        let fen = fen::to_fen(&board);
+        assert_eq!(
+            pgn,
+            format!(
+                "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+                 [White \"Alice\"]\n[Black \"?\"]\n[Result \"*\"]\n[FEN \"{fen}\"]\n\
+                 [SetUp \"1\"]\n\n1... c5 *\n"
+            )
+        );
+    }
+
+    #[test]
+    fn written_games_parse_back() {
+        let (tags, moves) = parse_game(OPERA_GAME).unwrap();
+        let pgn = write_game(&Board::default(), &moves, &tags);
+        assert!(pgn.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+        let (reparsed_tags, reparsed_moves) = parse_game(&pgn).unwrap();
+        assert_eq!(reparsed_moves, moves);
+        for (name, value) in &tags {
+            assert_eq!(&reparsed_tags[name], value);
+        }
+        assert!(pgn.trim_end().ends_with("17. Rd8# 1-0"));
+
+        // a game from a set up position keeps its starting position
+        let mut start = Board::default();
+        start.apply_uci_moves(&["e2e4", "e7e5"]).unwrap();
+        let (_, moves) =
+            parse_game(&format!("[FEN \"{}\"]\n\n2. Nf3 Nc6", fen::to_fen(&start))).unwrap();
+        let (_, reparsed) = parse_game(&write_game(&start, &moves, &HashMap::new())).unwrap();
+        assert_eq!(reparsed, moves);
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::r#move::Move;
+
+/// what the stored score says about the real score of the position
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// the real score is at least the stored one (the search failed high)
+    Lower,
+    /// the real score is at most the stored one (the search failed low)
+    Upper,
+}
+
+/// which entry to keep when two positions land in the same slot
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Replacement {
+    /// the newer entry always wins
+    Always,
+    /// the newer entry only wins if it was searched at least as deep
+    DepthPreferred,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Entry {
+    pub hash: u64,
+    pub depth: u32,
+    pub score: f32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+/// a fixed size cache of search results, indexed by zobrist hash. every slot has a lock
+/// of its own, so that searches on several threads can share a table
+pub struct TranspositionTable {
+    entries: Vec<Mutex<Option<Entry>>>,
+    replacement: Replacement,
+}
+
+impl TranspositionTable {
+    /// a table with room for `size` entries, panics if `size` is 0
+    pub fn new(size: usize, replacement: Replacement) -> Self {
+        assert!(size > 0, "transposition table needs at least one entry");
+        Self {
+            entries: (0..size).map(|_| Mutex::new(None)).collect(),
+            replacement,
+        }
+    }
+
+    /// the entry of the position with `hash`, if it's stored
+    pub fn get(&self, hash: u64) -> Option<Entry> {
+        self.slot(hash).filter(|e| e.hash == hash)
+    }
+
+    pub fn insert(&self, entry: Entry) {
+        let mut slot = self.slot(entry.hash);
+        let replace = match (self.replacement, *slot) {
+            (Replacement::Always, _) | (_, None) => true,
+            (Replacement::DepthPreferred, Some(old)) => entry.depth >= old.depth,
+        };
+        if replace {
+            *slot = Some(entry);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for entry in &mut self.entries {
+            *entry.get_mut().unwrap_or_else(PoisonError::into_inner) = None;
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// the slot of `hash`. a thread panicking while holding it can't have left a torn
+    /// entry behind, since entries are only ever written as a whole
+    fn slot(&self, hash: u64) -> MutexGuard<'_, Option<Entry>> {
+        let index = (hash % self.entries.len() as u64) as usize;
+        self.entries[index]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(hash: u64, depth: u32) -> Entry {
+        Entry {
+            hash,
+            depth,
+            score: depth as f32,
+            bound: Bound::Exact,
+            best_move: None,
+        }
+    }
+
+    #[test]
+    fn stores_and_finds_entries() {
+        let mut table = TranspositionTable::new(16, Replacement::Always);
+        assert!(table.get(3).is_none());
+        table.insert(entry(3, 2));
+        assert_eq!(table.get(3).unwrap().depth, 2);
+        // the same slot, but another position
+        assert!(table.get(3 + 16).is_none());
+        table.clear();
+        assert!(table.get(3).is_none());
+        assert_eq!(table.size(), 16);
+    }
+
+    #[test]
+    fn replacement_schemes() {
+        let always = TranspositionTable::new(16, Replacement::Always);
+        always.insert(entry(5, 4));
+        always.insert(entry(5 + 16, 1));
+        assert!(always.get(5).is_none());
+        assert_eq!(always.get(5 + 16).unwrap().depth, 1);
+        let deeper = TranspositionTable::new(16, Replacement::DepthPreferred);
+        deeper.insert(entry(5, 4));
+        deeper.insert(entry(5 + 16, 1));
+        assert_eq!(deeper.get(5).unwrap().depth, 4);
+        assert!(deeper.get(5 + 16).is_none());
+        deeper.insert(entry(5 + 16, 4));
+        assert_eq!(deeper.get(5 + 16).unwrap().depth, 4);
+    }
+}
@@ -0,0 +1,168 @@
+use std::io::{self, BufRead, Write};
+
+use crate::chess::{Board, Color};
+use crate::fen;
+use crate::r#move::Move;
+
+/// think this long if `go` doesn't say otherwise
+const DEFAULT_MOVE_TIME: u64 = 1000;
+/// with a clock, use this fraction of the remaining time for a move
+const MOVES_TO_GO: u64 = 30;
+
+/// the state of a UCI session: the position set up by the gui
+pub struct Engine {
+    board: Board,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self {
+            board: Board::default(),
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// answers a single command to `out`. returns false once the gui asks to quit.
+    /// unknown commands are ignored as the protocol demands
+    pub fn handle(&mut self, command: &str, out: &mut impl Write) -> io::Result<bool> {
+        let mut words = command.split_whitespace();
+        match words.next() {
+            Some("uci") => {
+                writeln!(out, "id name chessica")?;
+                writeln!(out, "id author sanj0")?;
+                writeln!(out, "uciok")?;
+            }
+            Some("isready") => writeln!(out, "readyok")?,
+            Some("ucinewgame") => *self = Self::new(),
+            Some("position") => {
+                if let Err(e) = self.set_position(&words.collect::<Vec<_>>()) {
+                    writeln!(out, "info string {e}")?;
+                }
+            }
+            Some("go") => {
+                let mv = self.go(&words.collect::<Vec<_>>());
+                match mv {
+                    Some(mv) => writeln!(out, "bestmove {}", mv.to_uci())?,
+                    None => writeln!(out, "bestmove 0000")?,
+                }
+            }
+            Some("quit") => return Ok(false),
+            _ => {}
+        }
+        out.flush()?;
+        Ok(true)
+    }
+
+    /// `position [startpos | fen <fen>] [moves <move>...]`
+    fn set_position(&mut self, args: &[&str]) -> Result<(), String> {
+        let moves_at = args
+            .iter()
+            .position(|&a| a == "moves")
+            .unwrap_or(args.len());
+        let mut board = match args.first() {
+            Some(&"startpos") => Board::default(),
+            Some(&"fen") => fen::parse_board(&args[1..moves_at].join(" "))?,
+            _ => return Err(String::from("position expects startpos or fen")),
+        };
+        board.apply_uci_moves(args.get(moves_at + 1..).unwrap_or_default())?;
+        self.board = board;
+        Ok(())
+    }
+
+    /// `go [depth <plies>] [movetime <ms>] [wtime <ms>] [btime <ms>] ...`,
+    /// answered from the opening book without searching while it knows the position
+    fn go(&self, args: &[&str]) -> Option<Move> {
+        if let Some(mv) = self.board.book_move() {
+            return Some(mv);
+        }
+        let value = |name: &str| {
+            args.iter()
+                .position(|&a| a == name)
+                .and_then(|i| args.get(i + 1))
+                .and_then(|v| v.parse::<u64>().ok())
+        };
+        if let Some(depth) = value("depth") {
+            return self.board.best_move(depth as u32);
+        }
+        let clock = if self.board.turn() == Color::White {
+            value("wtime")
+        } else {
+            value("btime")
+        };
+        let millis = value("movetime")
+            .or(clock.map(|t| t / MOVES_TO_GO))
+            .unwrap_or(DEFAULT_MOVE_TIME);
+        self.board.best_move_timed(millis.max(1))
+    }
+}
+
+/// answers the commands on `input` until it ends or says `quit`
+pub fn run(input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut engine = Engine::new();
+    for line in input.lines() {
+        if !engine.handle(&line?, &mut output)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(script: &str) -> String {
+        let mut out = Vec::new();
+        run(script.as_bytes(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn answers_a_scripted_session() {
+        let out = session(
+            "uci\nisready\nposition fen 4k3/8/8/3q4/8/8/8/3RK3 b - - 0 1 moves e8e7 e1e2\n\
+             go depth 2\nquit\ngo depth 1\n",
+        );
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[..4],
+            ["id name chessica", "id author sanj0", "uciok", "readyok"]
+        );
+        // nothing is answered after quit
+        assert_eq!(lines.len(), 5);
+        let best = lines[4].strip_prefix("bestmove ").unwrap();
+        let mut board = fen::parse_board("4k3/8/8/3q4/8/8/8/3RK3 b - - 0 1").unwrap();
+        board.apply_uci_moves(&["e8e7", "e1e2"]).unwrap();
+        let mv = Move::from_uci(best, &board).unwrap();
+        assert!(board.gen_legal(Color::Black).contains(&mv), "{best}");
+    }
+
+    #[test]
+    fn position_sets_up_the_board() {
+        let mut engine = Engine::new();
+        let mut out = Vec::new();
+        engine
+            .handle("position startpos moves e2e4 e7e5", &mut out)
+            .unwrap();
+        assert_eq!(
+            fen::to_fen(engine.board()),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+        // a bad position is reported and the old one kept
+        engine
+            .handle("position startpos moves e2e5", &mut out)
+            .unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with("info string"));
+        assert_eq!(engine.board().fullmove_number(), 2);
+        assert!(!engine.handle("quit", &mut Vec::new()).unwrap());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::chess::{Board, Color, Piece};
+
+/// random keys for every piece on every square, the side to move, every combination
+/// of castle rights and every en passant file, generated at compile time
+struct Keys {
+    pieces: [[u64; Board::NUM_SQUARES]; 12],
+    black_to_move: u64,
+    castle_rights: [u64; 16],
+    en_passant_file: [u64; 8],
+}
+
+const KEYS: Keys = generate_keys();
+
+// splitmix64, deterministic so that hashes are stable between runs
+const fn next(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+const fn generate_keys() -> Keys {
+    let mut state = 0x6368_6573_7369_6361;
+    let mut pieces = [[0; Board::NUM_SQUARES]; 12];
+    let mut i = 0;
+    while i < 12 {
+        let mut square = 0;
+        while square < Board::NUM_SQUARES {
+            pieces[i][square] = next(&mut state);
+            square += 1;
+        }
+        i += 1;
+    }
+    let black_to_move = next(&mut state);
+    let mut castle_rights = [0; 16];
+    let mut i = 0;
+    while i < 16 {
+        castle_rights[i] = next(&mut state);
+        i += 1;
+    }
+    let mut en_passant_file = [0; 8];
+    let mut i = 0;
+    while i < 8 {
+        en_passant_file[i] = next(&mut state);
+        i += 1;
+    }
+    Keys {
+        pieces,
+        black_to_move,
+        castle_rights,
+        en_passant_file,
+    }
+}
+
+/// the key of `piece` standing on `square`, 0 for an empty square
+pub fn piece_key(piece: Piece, square: usize) -> u64 {
+    if piece.kind() == 0 {
+        return 0;
+    }
+    let color = if piece.color() == Piece::WHITE { 0 } else { 6 };
+    // the kind bits start at Piece::PAWN = 1 << 3
+    let kind = piece.kind().trailing_zeros() as usize - 3;
+    KEYS.pieces[color + kind][square]
+}
+
+pub fn turn_key(turn: Color) -> u64 {
+    if turn == Color::Black {
+        KEYS.black_to_move
+    } else {
+        0
+    }
+}
+
+pub fn castle_key(castle_rights: u8) -> u64 {
+    KEYS.castle_rights[castle_rights as usize & 0b1111]
+}
+
+/// only the file of the en passant target is hashed, the rank follows from the turn.
+/// the target only counts if a pawn of the side to move could actually capture
+/// on it, so that positions that only differ in an unusable target hash equally
+pub fn en_passant_key(board: &Board) -> u64 {
+    let Some(target) = board.en_passant_target() else {
+        return 0;
+    };
+    let file = Board::file_of(target);
+    let rank = Board::rank_of(target);
+    // the capturing pawns stand one rank behind the target from their point of view
+    let pawn_rank = if board.turn() == Color::White {
+        rank.wrapping_sub(1)
+    } else {
+        rank + 1
+    };
+    if pawn_rank >= Board::NUM_RANKS as usize {
+        return 0;
+    }
+    let pawn = Piece::new_unchecked(board.turn().bits(), Piece::PAWN);
+    let capturable = [file.wrapping_sub(1), file + 1]
+        .into_iter()
+        .filter(|&f| f < Board::NUM_FILES as usize)
+        .any(|f| board[Board::square_index(f, pawn_rank)] == pawn);
+    if capturable {
+        KEYS.en_passant_file[file]
+    } else {
+        0
+    }
+}
+
+/// the hash of the board computed from scratch
+pub fn hash(board: &Board) -> u64 {
+    let mut hash =
+        turn_key(board.turn()) ^ castle_key(board.castle_rights()) ^ en_passant_key(board);
+    for square in 0..Board::NUM_SQUARES {
+        hash ^= piece_key(board[square], square);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::parse_board;
+
+    #[test]
+    fn same_positions_hash_equally() {
+        assert_eq!(Board::default().zobrist_hash(), hash(&Board::default()));
+        let mut a = Board::default();
+        let mut b = Board::default();
+        a.apply_uci_moves(&["g1f3", "g8f6", "b1c3"]).unwrap();
+        b.apply_uci_moves(&["b1c3", "g8f6", "g1f3"]).unwrap();
+        assert_eq!(a.zobrist_hash(), b.zobrist_hash());
+
+        let mut e4 = Board::default();
+        e4.apply_uci_moves(&["e2e4"]).unwrap();
+        assert_ne!(e4.zobrist_hash(), Board::default().zobrist_hash());
+        // no black pawn can take on e3, so the target doesn't count
+        let without_target =
+            parse_board("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_eq!(e4.zobrist_hash(), without_target.zobrist_hash());
+    }
+
+    #[test]
+    fn turn_castle_rights_and_en_passant_change_the_hash() {
+        let hashes = [
+            "4k3/8/8/3pP3/8/8/8/R3K3 w Q d6 0 1",
+            "4k3/8/8/3pP3/8/8/8/R3K3 w Q - 0 1",
+            "4k3/8/8/3pP3/8/8/8/R3K3 w - d6 0 1",
+            "4k3/8/8/3pP3/8/8/8/R3K3 b Q - 0 1",
+        ]
+        .map(|fen| parse_board(fen).unwrap().zobrist_hash());
+        for i in 0..hashes.len() {
+            for j in i + 1..hashes.len() {
+                assert_ne!(hashes[i], hashes[j], "{i} and {j}");
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_hash_matches_the_full_hash() {
+        let mut board =
+            parse_board("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        // walk through the position always playing the move at a rotating index,
+        // covering castles, captures, promotions and en passant along the way
+        for ply in 0..300 {
+            let moves = board.gen_legal(board.turn());
+            if moves.is_empty() {
+                break;
+            }
+            for &mv in &moves {
+                let after = board.with_move(mv);
+                assert_eq!(after.zobrist_hash(), hash(&after), "{mv:?}");
+            }
+            board.make_move(moves[ply * 7 % moves.len()]);
+        }
+    }
+}